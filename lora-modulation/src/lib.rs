@@ -218,6 +218,25 @@ mod tests {
     // * https://www.thethingsnetwork.org/airtime-calculator
     // * https://avbentem.github.io/airtime-calculator/ttn/
 
+    #[test]
+    fn low_data_rate_optimization() {
+        fn ldro(sf: SpreadingFactor, bw: Bandwidth) -> bool {
+            BaseBandModulationParams::new(sf, bw, CodingRate::_4_5).ldro
+        }
+        assert!(ldro(SpreadingFactor::_12, Bandwidth::_125KHz));
+        assert!(ldro(SpreadingFactor::_11, Bandwidth::_125KHz));
+        assert!(!ldro(SpreadingFactor::_10, Bandwidth::_125KHz));
+        assert!(!ldro(SpreadingFactor::_7, Bandwidth::_125KHz));
+        // SF12 at 250 kHz has the same symbol duration as SF11 at 125 kHz
+        assert!(ldro(SpreadingFactor::_12, Bandwidth::_250KHz));
+        assert!(!ldro(SpreadingFactor::_11, Bandwidth::_250KHz));
+        // LDRO lengthens the time on air, as fewer bits are sent per symbol
+        let no_ldro = BaseBandModulationParams { ldro: false, ..SF12BW125 };
+        assert!(
+            SF12BW125.time_on_air_us(Some(8), true, 51) > no_ldro.time_on_air_us(Some(8), true, 51)
+        );
+    }
+
     #[test]
     fn time_on_air_for_short_messages() {
         assert_eq!(1152, SF5BW500.time_on_air_us(None, true, 0));
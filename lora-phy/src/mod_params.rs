@@ -90,6 +90,13 @@ pub struct ModulationParams {
     pub(crate) frequency_in_hz: u32,
 }
 
+/// Whether low data rate optimization is required, ie: the symbol duration is at least 16.384 ms (SF11 at 125 kHz)
+pub(crate) fn low_data_rate_optimize(spreading_factor: SpreadingFactor, bandwidth: Bandwidth) -> u8 {
+    // SF and BW are the only parameters affecting the symbol duration
+    let params = lora_modulation::BaseBandModulationParams::new(spreading_factor, bandwidth, CodingRate::_4_5);
+    params.ldro as u8
+}
+
 /// Packet parameters for a send or receive communication channel
 pub struct PacketParams {
     pub(crate) preamble_length: u16,  // number of LoRa symbols in the preamble
@@ -118,3 +125,20 @@ pub struct DutyCycleParams {
     /// sleep interval
     pub sleep_time: u32,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_low_data_rate_optimize() {
+        assert_eq!(low_data_rate_optimize(SpreadingFactor::_12, Bandwidth::_125KHz), 1);
+        assert_eq!(low_data_rate_optimize(SpreadingFactor::_11, Bandwidth::_125KHz), 1);
+        assert_eq!(low_data_rate_optimize(SpreadingFactor::_10, Bandwidth::_125KHz), 0);
+        assert_eq!(low_data_rate_optimize(SpreadingFactor::_7, Bandwidth::_125KHz), 0);
+        assert_eq!(low_data_rate_optimize(SpreadingFactor::_12, Bandwidth::_250KHz), 1);
+        assert_eq!(low_data_rate_optimize(SpreadingFactor::_11, Bandwidth::_250KHz), 0);
+        assert_eq!(low_data_rate_optimize(SpreadingFactor::_12, Bandwidth::_500KHz), 0);
+        assert_eq!(low_data_rate_optimize(SpreadingFactor::_10, Bandwidth::_62KHz), 1);
+    }
+}
@@ -8,13 +8,13 @@ pub use super::{
     region::{self, Region},
     Downlink, JoinMode,
 };
-use core::marker::PhantomData;
 use heapless::Vec;
 use lorawan::{self, keys::CryptoFactory};
 use rand_core::RngCore;
 
 pub use crate::region::DR;
-use crate::{radio::RadioBuffer, rng};
+use crate::{radio::RadioBuffer, rng, AppSKey, NwkSKey};
+use radio::RxQuality;
 
 pub mod radio;
 
@@ -47,10 +47,10 @@ pub struct Device<R, C, T, G, const N: usize = 256, const D: usize = 1>
 where
     R: radio::PhyRxTx + Timings,
     T: radio::Timer,
-    C: CryptoFactory + Default,
+    C: CryptoFactory,
     G: RngCore,
 {
-    crypto: PhantomData<C>,
+    crypto: C,
     radio: R,
     /// Access to provided (pseudo)-random number generator.
     pub rng: G,
@@ -154,13 +154,35 @@ where
         timer: T,
         rng: G,
         session: Option<Session>,
+    ) -> Self {
+        Device::new_with_crypto(region, radio, timer, rng, C::default(), session)
+    }
+}
+
+impl<R, C, T, G, const N: usize, const D: usize> Device<R, C, T, G, N, D>
+where
+    R: radio::PhyRxTx + Timings,
+    C: CryptoFactory,
+    T: radio::Timer,
+    G: RngCore,
+{
+    /// Create a new [`Device`] using the given crypto factory instance, eg: a
+    /// [`lorawan::default_crypto::Crypto`] selecting hardware or software AES at runtime, and
+    /// provide an optional [`Session`].
+    pub fn new_with_crypto(
+        region: region::Configuration,
+        radio: R,
+        timer: T,
+        rng: G,
+        crypto: C,
+        session: Option<Session>,
     ) -> Self {
         let mut mac = Mac::new(region, R::MAX_RADIO_POWER, R::ANTENNA_GAIN);
         if let Some(session) = session {
             mac.set_session(session);
         }
         Self {
-            crypto: PhantomData,
+            crypto,
             radio,
             rng,
             mac,
@@ -190,6 +212,76 @@ where
         self.mac.get_session()
     }
 
+    /// Request a link check from the network with the next uplink. The answer can be retrieved
+    /// with [`Self::take_link_check_ans`] once a downlink was received.
+    pub fn request_link_check(&mut self) -> Result<(), Error<R::PhyError>> {
+        Ok(self.mac.request_link_check()?)
+    }
+
+    /// Take the answer to the last link check request, if one was received.
+    pub fn take_link_check_ans(&mut self) -> Option<mac::LinkCheckAns> {
+        self.mac.take_link_check_ans()
+    }
+
+    /// Request the network time with the next uplink, eg: for keeping a Class B clock aligned. The
+    /// answer can be retrieved with [`Self::take_device_time_ans`] once a downlink was received.
+    pub fn request_device_time(&mut self) -> Result<(), Error<R::PhyError>> {
+        Ok(self.mac.request_device_time()?)
+    }
+
+    /// Take the answer to the last device time request, if one was received.
+    pub fn take_device_time_ans(&mut self) -> Option<mac::DeviceTimeAns> {
+        self.mac.take_device_time_ans()
+    }
+
+    /// Apply a single downlink MAC command as if it had been received from the network and return
+    /// its answer, if any. The answer is not queued for the next uplink. Meant for testing and
+    /// debugging the handling of individual MAC commands.
+    pub fn apply_mac_command(
+        &mut self,
+        cmd: lorawan::maccommands::DownlinkMacCommand<'_>,
+    ) -> Option<lorawan::maccommands::UplinkMacCommandCreator> {
+        self.mac.apply_mac_command(cmd)
+    }
+
+    /// Enable or disable uplink dwell time limitations (eg: AS923), which reduce the maximum
+    /// payload size. While enabled, sending a larger payload fails with
+    /// [`mac::Error::DwellTimeExceeded`].
+    pub fn set_uplink_dwell_time(&mut self, enabled: bool) {
+        self.mac.set_uplink_dwell_time(enabled)
+    }
+
+    /// Set the maximum number of MAC commands processed per downlink (default:
+    /// [`mac::DEFAULT_MAX_DOWNLINK_MAC_COMMANDS`]). Further commands of the downlink are ignored,
+    /// guarding against downlinks stuffed with commands, and reported with
+    /// [`mac::MacEvent::MacCommandsDropped`].
+    pub fn set_max_downlink_mac_commands(&mut self, max: usize) {
+        self.mac.set_max_downlink_mac_commands(max)
+    }
+
+    /// Set the battery level reported to the network in DevStatusAns: 0 for an external power
+    /// source, 1 (minimum) to 254 (maximum) for the battery level and 255 if it cannot be measured
+    /// (default).
+    pub fn set_battery_level(&mut self, battery_level: u8) {
+        self.mac.set_battery_level(battery_level)
+    }
+
+    /// RSSI and SNR of the last frame accepted in a receive window, ie: a JoinAccept or a
+    /// downlink of the session passing the MIC check, if any.
+    pub fn last_rx_quality(&self) -> Option<RxQuality> {
+        self.mac.last_rx_quality()
+    }
+
+    /// Replace the keys of an ABP session which were rotated out-of-band, resetting FCntUp and
+    /// FCntDown to 0. Returns an error if the device is not joined.
+    pub fn rekey_abp(
+        &mut self,
+        nwkskey: NwkSKey,
+        appskey: AppSKey,
+    ) -> Result<(), Error<R::PhyError>> {
+        Ok(self.mac.rekey_abp(nwkskey, appskey)?)
+    }
+
     pub fn get_region(&mut self) -> &region::Configuration {
         &self.mac.region
     }
@@ -212,6 +304,49 @@ where
         self.mac.configuration.data_rate = datarate;
     }
 
+    /// Maximum application payload size for the next uplink, accounting for the current data
+    /// rate and any pending MAC commands which will be sent in FOpts.
+    pub fn available_app_payload(&self) -> usize {
+        self.mac.available_app_payload()
+    }
+
+    /// Configure the NetID the device expects to be assigned a DevAddr from when joining. A
+    /// mismatch does not fail the join, but can be checked with [`Self::devaddr_matches_net_id`].
+    pub fn set_expected_net_id(&mut self, net_id: Option<u32>) {
+        self.mac.set_expected_net_id(net_id)
+    }
+
+    /// Use a counter for the DevNonce of join requests, as required since LoRaWAN 1.0.4, instead
+    /// of random values. `next` is the DevNonce to use for the next join, eg: 0 for a new device,
+    /// or the value of [`Self::dev_nonce_counter`] persisted before a reset. Once all 2^16 values
+    /// have been used, joining fails with [`mac::Error::DevNonceExhausted`].
+    pub fn set_dev_nonce_counter(&mut self, next: Option<u32>) {
+        self.mac.set_dev_nonce_counter(next)
+    }
+
+    /// Returns the DevNonce to use for the next join if a counter is used. It is incremented by
+    /// each join request, so it should be persisted after joining.
+    pub fn dev_nonce_counter(&self) -> Option<u32> {
+        self.mac.dev_nonce_counter()
+    }
+
+    /// Configure after how many consecutive downlinks failing the MIC check a rejoin is
+    /// recommended with [`mac::MacEvent::RejoinRecommended`]. Disabled (`None`) by default.
+    pub fn set_mic_failure_threshold(&mut self, threshold: Option<u16>) {
+        self.mac.set_mic_failure_threshold(threshold)
+    }
+
+    /// Take the next pending MAC event, if any. See [`mac::MacEvent`].
+    pub fn take_mac_event(&mut self) -> Option<mac::MacEvent> {
+        self.mac.take_mac_event()
+    }
+
+    /// Returns whether the DevAddr of the current session belongs to the expected NetID, or `None`
+    /// if the device is not joined or no NetID is configured.
+    pub fn devaddr_matches_net_id(&self) -> Option<bool> {
+        self.mac.devaddr_matches_net_id()
+    }
+
     /// Join the LoRaWAN network asynchronously. The returned future completes when
     /// the LoRaWAN network has been joined successfully, or an error has occurred.
     ///
@@ -223,10 +358,11 @@ where
         match join_mode {
             JoinMode::OTAA { deveui, appeui, appkey } => {
                 let (tx_config, _) = self.mac.join_otaa::<C, G, N>(
+                    &self.crypto,
                     &mut self.rng,
                     NetworkCredentials::new(*appeui, *deveui, *appkey),
                     &mut self.radio_buffer,
-                );
+                )?;
 
                 // Transmit the join payload
                 let ms = self
@@ -246,6 +382,27 @@ where
         }
     }
 
+    /// Join the LoRaWAN network by trying each of the candidate regions in turn, eg: when the
+    /// device does not know which region it is deployed in. The returned future completes as soon
+    /// as a JoinAccept is received, keeping the region it was received in (see
+    /// [`Self::get_region`]), or when all the candidates were tried.
+    ///
+    /// Each attempt starts from the default configuration of the region, so region settings such
+    /// as the join bias should be applied again once a region was found.
+    pub async fn join_scan(
+        &mut self,
+        join_mode: &JoinMode,
+        regions: &[Region],
+    ) -> Result<JoinResponse, Error<R::PhyError>> {
+        for region in regions {
+            self.mac.set_region(region::Configuration::new(*region));
+            if let JoinResponse::JoinSuccess = self.join(join_mode).await? {
+                return Ok(JoinResponse::JoinSuccess);
+            }
+        }
+        Ok(JoinResponse::NoJoinAccept)
+    }
+
     /// Send data on a given port with the expected confirmation. If downlink data is provided, the
     /// data is copied into the provided byte slice.
     ///
@@ -264,6 +421,7 @@ where
     ) -> Result<SendResponse, Error<R::PhyError>> {
         // Prepare transmission buffer
         let (tx_config, _fcnt_up) = self.mac.send::<C, G, N>(
+            &self.crypto,
             &mut self.rng,
             &mut self.radio_buffer,
             &SendData { data, fport, confirmed },
@@ -375,13 +533,15 @@ where
             )
             .await
             {
-                RxcWindowResponse::Rx(sz, _, timeout_fut) => {
+                RxcWindowResponse::Rx(sz, q, timeout_fut) => {
                     debug!("RXC window received {} bytes.", sz);
                     self.radio_buffer.set_pos(sz);
-                    match self
-                        .mac
-                        .handle_rxc::<C, N, D>(&mut self.radio_buffer, &mut self.downlink)?
-                    {
+                    match self.mac.handle_rxc::<C, N, D>(
+                        &self.crypto,
+                        &mut self.radio_buffer,
+                        q,
+                        &mut self.downlink,
+                    )? {
                         mac::Response::NoUpdate => {
                             debug!("RXC frame was invalid.");
                             self.radio_buffer.clear();
@@ -453,10 +613,14 @@ where
     async fn rx_listen(&mut self) -> Result<Option<mac::Response>, Error<R::PhyError>> {
         let response =
             match self.radio.rx_single(self.radio_buffer.as_mut()).await.map_err(Error::Radio)? {
-                RxStatus::Rx(s, _q) => {
+                RxStatus::Rx(s, q) => {
                     self.radio_buffer.set_pos(s);
-                    match self.mac.handle_rx::<C, N, D>(&mut self.radio_buffer, &mut self.downlink)
-                    {
+                    match self.mac.handle_rx::<C, N, D>(
+                        &self.crypto,
+                        &mut self.radio_buffer,
+                        q,
+                        &mut self.downlink,
+                    ) {
                         mac::Response::NoUpdate => None,
                         r => Some(r),
                     }
@@ -472,10 +636,15 @@ where
     /// listening to RXC frames. The caller is expected to be awaiting this message at all times.
     pub async fn rxc_listen(&mut self) -> Result<mac::Response, Error<R::PhyError>> {
         loop {
-            let (sz, _rx_quality) =
+            let (sz, rx_quality) =
                 self.radio.rx_continuous(self.radio_buffer.as_mut()).await.map_err(Error::Radio)?;
             self.radio_buffer.set_pos(sz);
-            match self.mac.handle_rxc::<C, N, D>(&mut self.radio_buffer, &mut self.downlink)? {
+            match self.mac.handle_rxc::<C, N, D>(
+                &self.crypto,
+                &mut self.radio_buffer,
+                rx_quality,
+                &mut self.downlink,
+            )? {
                 mac::Response::NoUpdate => {
                     self.radio_buffer.clear();
                 }
@@ -89,6 +89,31 @@ async fn test_no_join_accept() {
     }
 }
 
+#[tokio::test]
+async fn test_join_scan_locks_region() {
+    let (radio, timer, mut async_device) = setup();
+    // Run the device
+    let async_device = tokio::spawn(async move {
+        let response = async_device
+            .join_scan(&get_otaa_credentials(), &[region::Region::EU868, region::Region::US915])
+            .await;
+        (async_device, response)
+    });
+
+    // No JoinAccept in EU868
+    timer.fire_most_recent().await;
+    radio.handle_timeout().await;
+    timer.fire_most_recent().await;
+    radio.handle_timeout().await;
+    // JoinAccept in RX1 in US915
+    timer.fire_most_recent().await;
+    radio.handle_rxtx(handle_join_request::<3>).await;
+
+    let (mut async_device, response) = async_device.await.unwrap();
+    assert!(matches!(response, Ok(JoinResponse::JoinSuccess)));
+    assert_eq!(async_device.get_region().get_current_region(), region::Region::US915);
+}
+
 #[tokio::test]
 async fn test_unconfirmed_uplink_no_downlink() {
     let (radio, timer, mut async_device) = setup_with_session();
@@ -24,6 +24,8 @@ pub fn setup_with_session() -> (RadioChannel, TimerChannel, Device) {
         devaddr: get_dev_addr(),
         fcnt_up: 0,
         fcnt_down: 0,
+        downlink_received: false,
+        mic_failures: 0,
         confirmed: false,
         uplink: Default::default(),
     }))
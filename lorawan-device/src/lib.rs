@@ -28,7 +28,6 @@ pub mod async_device;
 pub mod nb_device;
 use nb_device::state::State;
 
-use core::marker::PhantomData;
 #[cfg(feature = "default-crypto")]
 #[cfg_attr(docsrs, doc(cfg(feature = "default-crypto")))]
 pub use lorawan::default_crypto;
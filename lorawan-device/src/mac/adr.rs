@@ -0,0 +1,87 @@
+//! Device-managed ADR helpers, for when the network does not drive the data rate.
+//!
+//! Follows the usual network-side approach: the best recent link margin, minus an installation
+//! margin, is spent in steps of 3 dB, first on raising the data rate and then on lowering the
+//! transmit power.
+
+/// Link margin in dB gained or lost by changing the data rate or the TX power by one step.
+pub const STEP_DB: u8 = 3;
+/// Default margin in dB kept above the demodulation floor.
+pub const DEFAULT_INSTALLATION_MARGIN: u8 = 10;
+
+/// Change suggested by [`AdrController::suggest`].
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub enum AdrAction {
+    /// Raise the data rate index by the given number of steps.
+    IncreaseDataRate(u8),
+    /// Lower the TX power index by the given number of steps (ie: increase the TXPower field of
+    /// a LinkADRReq).
+    DecreaseTxPower(u8),
+}
+
+/// Suggests data rate and TX power changes from the link margins observed recently, eg: the
+/// margins of [`LinkCheckAns`](super::LinkCheckAns).
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub struct AdrController {
+    /// Margin in dB kept above the demodulation floor.
+    pub installation_margin: u8,
+    /// Current data rate index.
+    pub data_rate: u8,
+    /// Highest data rate index usable in the region.
+    pub max_data_rate: u8,
+}
+
+impl AdrController {
+    pub fn new(data_rate: u8, max_data_rate: u8) -> Self {
+        Self { installation_margin: DEFAULT_INSTALLATION_MARGIN, data_rate, max_data_rate }
+    }
+
+    /// Suggests an ADR action given the link margins in dB of recent uplinks. Returns `None` if
+    /// there are no margins or not enough of it to take a step.
+    ///
+    /// The data rate is raised first; the TX power is only lowered once the maximum data rate is
+    /// reached.
+    pub fn suggest(&self, recent_margins: &[u8]) -> Option<AdrAction> {
+        let best = recent_margins.iter().max()?;
+        let steps = best.saturating_sub(self.installation_margin) / STEP_DB;
+        if steps == 0 {
+            return None;
+        }
+        let dr_steps = self.max_data_rate.saturating_sub(self.data_rate).min(steps);
+        if dr_steps > 0 {
+            Some(AdrAction::IncreaseDataRate(dr_steps))
+        } else {
+            Some(AdrAction::DecreaseTxPower(steps))
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_high_margin_suggests_data_rate_increase() {
+        let adr = AdrController::new(0, 5);
+        // best margin 20 dB: (20 - 10) / 3 = 3 steps
+        assert_eq!(adr.suggest(&[18, 20, 19, 17]), Some(AdrAction::IncreaseDataRate(3)));
+        // limited by the maximum data rate
+        let adr = AdrController::new(4, 5);
+        assert_eq!(adr.suggest(&[20; 4]), Some(AdrAction::IncreaseDataRate(1)));
+    }
+
+    #[test]
+    fn test_max_data_rate_suggests_tx_power_decrease() {
+        let adr = AdrController::new(5, 5);
+        assert_eq!(adr.suggest(&[20; 4]), Some(AdrAction::DecreaseTxPower(3)));
+    }
+
+    #[test]
+    fn test_low_margin_suggests_nothing() {
+        let adr = AdrController::new(0, 5);
+        assert_eq!(adr.suggest(&[12, 8, 5]), None);
+        assert_eq!(adr.suggest(&[]), None);
+    }
+}
@@ -3,21 +3,28 @@
 //! decrypting from send and receive buffers.
 
 use crate::{
-    radio::{self, RadioBuffer, RfConfig, RxConfig, RxMode},
+    radio::{self, RadioBuffer, RfConfig, RxConfig, RxMode, RxQuality},
     region, AppSKey, Downlink, NwkSKey,
 };
 use heapless::Vec;
 use lorawan::{self, keys::CryptoFactory};
-use lorawan::{maccommands::DownlinkMacCommand, parser::DevAddr};
+use lorawan::{
+    maccommands::{DownlinkMacCommand, LinkADRReqPayload, UplinkMacCommandCreator},
+    parser::{DecryptedJoinAcceptPayload, DevAddr},
+};
 
 pub type FcntDown = u32;
 pub type FcntUp = u32;
 
+/// Default maximum number of MAC commands processed per downlink.
+pub const DEFAULT_MAX_DOWNLINK_MAC_COMMANDS: usize = 16;
+
 mod session;
 use rand_core::RngCore;
 pub use session::{Session, SessionKeys};
 
 mod otaa;
+use otaa::DevNonce;
 pub use otaa::NetworkCredentials;
 
 use crate::async_device;
@@ -25,6 +32,9 @@ use crate::nb_device;
 
 pub(crate) mod uplink;
 
+pub mod adr;
+pub mod ping_slot;
+
 #[derive(Copy, Clone, Debug)]
 pub(crate) enum Frame {
     Join,
@@ -45,36 +55,177 @@ pub struct Configuration {
     rx1_delay: u32,
     join_accept_delay1: u32,
     join_accept_delay2: u32,
+    expected_net_id: Option<u32>,
+    battery_level: u8,
+    last_rx_quality: Option<RxQuality>,
+    link_check_ans: Option<LinkCheckAns>,
+    device_time_ans: Option<DeviceTimeAns>,
+    uplink_dwell_time: bool,
+    max_downlink_mac_commands: usize,
+    mic_failure_threshold: Option<u16>,
+    rejoin_recommended: bool,
+    dropped_mac_commands: usize,
+    /// Next DevNonce to use when they are counted (LoRaWAN 1.0.4) rather than random.
+    next_dev_nonce: Option<u32>,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+/// Answer of the network to a link check request.
+pub struct LinkCheckAns {
+    /// Link margin in dB of the last successfully received LinkCheckReq.
+    pub margin: u8,
+    /// Number of gateways which successfully received the last LinkCheckReq.
+    pub gateway_count: u8,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+/// Network time given in answer to a device time request, valid at the end of the uplink
+/// transmission which carried the request.
+pub struct DeviceTimeAns {
+    /// Seconds since the GPS epoch.
+    pub seconds: u32,
+    /// Fractional second in nanoseconds, with a 1/256 s resolution.
+    pub nano_seconds: u32,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+/// Events raised by the MAC layer outside of the responses to sending and receiving.
+pub enum MacEvent {
+    /// The configured number of consecutive downlinks addressed to the device failed the MIC
+    /// check, which usually means that the session keys are out of sync with the network and the
+    /// device should join again.
+    RejoinRecommended,
+    /// MAC commands of downlinks were dropped since the last event: those over the per downlink
+    /// limit, see `set_max_downlink_mac_commands`, and the ones whose answer could not be queued.
+    MacCommandsDropped(usize),
 }
 
 impl Configuration {
-    fn handle_downlink_macs(
+    /// Checks whether the NwkID bits of `devaddr` match the configured NetID. Returns `None` if
+    /// no NetID is configured.
+    fn devaddr_matches_net_id(&self, devaddr: &DevAddr<[u8; 4]>) -> Option<bool> {
+        self.expected_net_id.map(|net_id| {
+            // the 3 MSB of the NetID give its type, its LSB form the NwkID of the DevAddrs of the
+            // same type
+            devaddr.parts().is_some_and(|parts| {
+                u32::from(parts.addr_type) == (net_id >> 21) & 0x7
+                    && parts.nwk_id == net_id & ((1 << parts.nwk_id_len()) - 1)
+            })
+        })
+    }
+
+    /// Records the number of consecutive downlinks which failed the MIC check, recommending a
+    /// rejoin once it reaches the configured threshold.
+    fn record_mic_failures(&mut self, mic_failures: u16) {
+        if self.mic_failure_threshold == Some(mic_failures) {
+            self.rejoin_recommended = true;
+        }
+    }
+
+    /// Handles the MAC commands of a downlink, up to `max_downlink_mac_commands`. Remaining
+    /// commands are dropped and reported with [`MacEvent::MacCommandsDropped`].
+    fn handle_downlink_macs<'a>(
         &mut self,
         region: &mut region::Configuration,
         uplink: &mut uplink::Uplink,
-        cmds: lorawan::maccommands::MacCommandIterator<'_, DownlinkMacCommand<'_>>,
+        mut cmds: impl Iterator<Item = DownlinkMacCommand<'a>>,
     ) {
-        use uplink::MacAnsTrait;
-        for cmd in cmds {
-            match cmd {
-                DownlinkMacCommand::LinkADRReq(payload) => {
-                    // we ignore DR and TxPwr
-                    region.set_channel_mask(
-                        payload.redundancy().channel_mask_control(),
-                        payload.channel_mask(),
-                    );
-                    uplink.adr_ans.add();
-                }
-                DownlinkMacCommand::RXTimingSetupReq(payload) => {
-                    self.rx1_delay = del_to_delay_ms(payload.delay());
-                    uplink.ack_rx_delay();
+        let mut limited = cmds.by_ref().take(self.max_downlink_mac_commands).peekable();
+        while let Some(cmd) = limited.next() {
+            if let DownlinkMacCommand::LinkADRReq(payload) = cmd {
+                // contiguous LinkADRReqs form a block whose channel masks are applied together
+                let block = core::iter::once(payload).chain(core::iter::from_fn(|| match limited
+                    .next_if(|cmd| matches!(cmd, DownlinkMacCommand::LinkADRReq(_)))
+                {
+                    Some(DownlinkMacCommand::LinkADRReq(payload)) => Some(payload),
+                    _ => None,
+                }));
+                Self::handle_link_adr_reqs(region, uplink, block);
+            } else {
+                self.handle_downlink_mac(region, uplink, cmd);
+            }
+        }
+        let ignored = cmds.count();
+        if ignored > 0 {
+            warn!("Ignoring {} MAC commands over the per downlink limit.", ignored);
+            self.dropped_mac_commands = self.dropped_mac_commands.saturating_add(ignored);
+        }
+    }
+
+    /// Handles a block of contiguous LinkADRReqs, answering each of them with the same status.
+    fn handle_link_adr_reqs<'a>(
+        region: &mut region::Configuration,
+        uplink: &mut uplink::Uplink,
+        reqs: impl Iterator<Item = LinkADRReqPayload<'a>>,
+    ) {
+        let mut count = 0;
+        // we ignore DR and TxPwr
+        let channel_mask_ack = region.set_channel_masks(reqs.map(|payload| {
+            count += 1;
+            (payload.redundancy().channel_mask_control(), payload.channel_mask())
+        }));
+        uplink.ack_link_adr(count, channel_mask_ack);
+    }
+
+    fn handle_downlink_mac(
+        &mut self,
+        region: &mut region::Configuration,
+        uplink: &mut uplink::Uplink,
+        cmd: DownlinkMacCommand<'_>,
+    ) {
+        match cmd {
+            DownlinkMacCommand::LinkADRReq(payload) => {
+                Self::handle_link_adr_reqs(region, uplink, core::iter::once(payload));
+            }
+            DownlinkMacCommand::RXTimingSetupReq(payload) => {
+                self.rx1_delay = del_to_delay_ms(payload.delay());
+                uplink.ack_rx_delay();
+            }
+            DownlinkMacCommand::DevStatusReq(_) => {
+                // the margin is the SNR of the frame carrying the DevStatusReq
+                let margin = self.last_rx_quality.map_or(0, |quality| quality.snr());
+                uplink.ack_dev_status(self.battery_level, margin);
+            }
+            DownlinkMacCommand::NewChannelReq(payload) => {
+                let (channel_frequency_ack, data_rate_range_ack) = region.handle_new_channel(
+                    payload.channel_index(),
+                    payload.frequency().value(),
+                    payload.data_rate_range(),
+                );
+                if !uplink.ack_new_channel(channel_frequency_ack, data_rate_range_ack) {
+                    warn!("Too many pending NewChannelAns, ignoring NewChannelReq.");
+                    self.dropped_mac_commands = self.dropped_mac_commands.saturating_add(1);
                 }
-                _ => (),
             }
+            DownlinkMacCommand::LinkCheckAns(payload) => {
+                self.link_check_ans = Some(LinkCheckAns {
+                    margin: payload.margin(),
+                    gateway_count: payload.gateway_count(),
+                });
+            }
+            DownlinkMacCommand::DeviceTimeAns(payload) => {
+                self.device_time_ans = Some(DeviceTimeAns {
+                    seconds: payload.seconds(),
+                    nano_seconds: payload.nano_seconds(),
+                });
+            }
+            _ => (),
         }
     }
 }
 
+/// Which settings of the device were changed by applying a JoinAccept.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub(crate) struct JoinAcceptChanges {
+    pub rx1_delay: bool,
+    /// RX1 data rate offset or RX2 data rate
+    pub dl_settings: bool,
+    pub channels: bool,
+}
+
 pub(crate) struct Mac {
     pub configuration: Configuration,
     pub region: region::Configuration,
@@ -99,6 +250,26 @@ enum State {
 pub enum Error {
     NotJoined,
     InvalidResponse(Response),
+    /// The application payload exceeds the maximum size allowed while uplink dwell time
+    /// limitations are in effect.
+    DwellTimeExceeded {
+        max: usize,
+    },
+    /// All the DevNonces of the counter have been used, so the device cannot join anymore.
+    DevNonceExhausted,
+}
+
+impl core::fmt::Display for Error {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Error::NotJoined => f.write_str("not joined"),
+            Error::InvalidResponse(r) => write!(f, "invalid response: {r:?}"),
+            Error::DwellTimeExceeded { max } => {
+                write!(f, "payload exceeds the dwell time limit of {max} bytes")
+            }
+            Error::DevNonceExhausted => f.write_str("DevNonce counter exhausted"),
+        }
+    }
 }
 
 pub struct SendData<'a> {
@@ -121,25 +292,66 @@ impl Mac {
                 rx1_delay: region::constants::RECEIVE_DELAY1,
                 join_accept_delay1: region::constants::JOIN_ACCEPT_DELAY1,
                 join_accept_delay2: region::constants::JOIN_ACCEPT_DELAY2,
+                expected_net_id: None,
+                // the device is not able to measure its battery level until told otherwise
+                battery_level: 255,
+                last_rx_quality: None,
+                link_check_ans: None,
+                device_time_ans: None,
+                uplink_dwell_time: false,
+                max_downlink_mac_commands: DEFAULT_MAX_DOWNLINK_MAC_COMMANDS,
+                mic_failure_threshold: None,
+                rejoin_recommended: false,
+                dropped_mac_commands: 0,
+                next_dev_nonce: None,
             },
         }
     }
 
+    /// Replaces the region configuration, resetting the data rate to the default of the new region.
+    pub(crate) fn set_region(&mut self, region: region::Configuration) {
+        self.configuration.data_rate = region.get_default_datarate();
+        self.region = region;
+    }
+
     /// Prepare the radio buffer with transmitting a join request frame and provides the radio
     /// configuration for the transmission.
-    pub(crate) fn join_otaa<C: CryptoFactory + Default, RNG: RngCore, const N: usize>(
+    pub(crate) fn join_otaa<C: CryptoFactory, RNG: RngCore, const N: usize>(
         &mut self,
+        crypto: &C,
         rng: &mut RNG,
         credentials: NetworkCredentials,
         buf: &mut RadioBuffer<N>,
-    ) -> (radio::TxConfig, u16) {
+    ) -> Result<(radio::TxConfig, u16)> {
+        let dev_nonce = self.next_dev_nonce(rng)?;
         let mut otaa = otaa::Otaa::new(credentials);
-        let dev_nonce = otaa.prepare_buffer::<C, RNG, N>(rng, buf);
+        let dev_nonce = otaa.prepare_buffer::<C, N>(crypto, dev_nonce, buf);
         self.state = State::Otaa(otaa);
         let mut tx_config =
             self.region.create_tx_config(rng, self.configuration.data_rate, &Frame::Join);
         tx_config.adjust_power(self.board_eirp.max_power, self.board_eirp.antenna_gain);
-        (tx_config, dev_nonce)
+        Ok((tx_config, dev_nonce))
+    }
+
+    /// Gives the DevNonce for the next join request: the next value of the counter if one is
+    /// set, a random one otherwise. A counted DevNonce is never given twice.
+    pub(crate) fn next_dev_nonce<RNG: RngCore>(&mut self, rng: &mut RNG) -> Result<DevNonce> {
+        match self.configuration.next_dev_nonce {
+            Some(next) => {
+                let dev_nonce = u16::try_from(next).map_err(|_| Error::DevNonceExhausted)?;
+                self.configuration.next_dev_nonce = Some(next + 1);
+                Ok(DevNonce::from(dev_nonce))
+            }
+            None => Ok(DevNonce::from(rng.next_u32() as u16)),
+        }
+    }
+
+    pub(crate) fn set_dev_nonce_counter(&mut self, next: Option<u32>) {
+        self.configuration.next_dev_nonce = next;
+    }
+
+    pub(crate) fn dev_nonce_counter(&self) -> Option<u32> {
+        self.configuration.next_dev_nonce
     }
 
     /// Join via ABP. This does not transmit a join request frame, but instead sets the session.
@@ -152,6 +364,18 @@ impl Mac {
         self.state = State::Joined(Session::new(nwkskey, appskey, devaddr));
     }
 
+    /// Replace the session keys of an ABP session which were rotated out-of-band. The frame
+    /// counters are reset to 0, while the DevAddr and the region state are kept.
+    pub(crate) fn rekey_abp(&mut self, nwkskey: NwkSKey, appskey: AppSKey) -> Result<()> {
+        match &mut self.state {
+            State::Joined(session) => {
+                session.rekey(nwkskey, appskey);
+                Ok(())
+            }
+            State::Otaa(_) | State::Unjoined => Err(Error::NotJoined),
+        }
+    }
+
     /// Join via ABP. This does not transmit a join request frame, but instead sets the session.
     pub(crate) fn set_session(&mut self, session: Session) {
         self.state = State::Joined(session);
@@ -159,14 +383,20 @@ impl Mac {
 
     /// Prepare the radio buffer for transmitting a data frame and provide the radio configuration
     /// for the transmission. Returns an error if the device is not joined.
-    pub(crate) fn send<C: CryptoFactory + Default, RNG: RngCore, const N: usize>(
+    pub(crate) fn send<C: CryptoFactory, RNG: RngCore, const N: usize>(
         &mut self,
+        crypto: &C,
         rng: &mut RNG,
         buf: &mut RadioBuffer<N>,
         send_data: &SendData<'_>,
     ) -> Result<(radio::TxConfig, FcntUp)> {
+        let dwell_time_max =
+            self.configuration.uplink_dwell_time.then(|| self.available_app_payload());
         let fcnt = match &mut self.state {
-            State::Joined(ref mut session) => Ok(session.prepare_buffer::<C, N>(send_data, buf)),
+            State::Joined(ref mut session) => match dwell_time_max {
+                Some(max) if send_data.data.len() > max => Err(Error::DwellTimeExceeded { max }),
+                _ => Ok(session.prepare_buffer::<C, N>(crypto, send_data, buf)),
+            },
             State::Otaa(_) => Err(Error::NotJoined),
             State::Unjoined => Err(Error::NotJoined),
         }?;
@@ -206,14 +436,19 @@ impl Mac {
     /// Handles a received RF frame. Returns None is unparseable, fails decryption, or fails MIC
     /// verification. Upon successful join, provides Response::JoinSuccess. Upon successful data
     /// rx, provides Response::DownlinkReceived. User must take the downlink from vec for
-    /// application data.
-    pub(crate) fn handle_rx<C: CryptoFactory + Default, const N: usize, const D: usize>(
+    /// application data. The `quality` of the frame is only recorded if it is accepted.
+    pub(crate) fn handle_rx<C: CryptoFactory, const N: usize, const D: usize>(
         &mut self,
+        crypto: &C,
         buf: &mut RadioBuffer<N>,
+        quality: RxQuality,
         dl: &mut Vec<Downlink, D>,
     ) -> Response {
-        match &mut self.state {
+        // recorded beforehand, as it is the margin of a DevStatusAns answering the frame
+        let previous_quality = self.configuration.last_rx_quality.replace(quality);
+        let response = match &mut self.state {
             State::Joined(ref mut session) => session.handle_rx::<C, N, D>(
+                crypto,
                 &mut self.region,
                 &mut self.configuration,
                 buf,
@@ -221,9 +456,10 @@ impl Mac {
                 false,
             ),
             State::Otaa(ref mut otaa) => {
-                if let Some(session) =
-                    otaa.handle_rx::<C, N>(&mut self.region, &mut self.configuration, buf)
+                if let Some((session, join_accept)) =
+                    otaa.handle_rx::<C, N>(crypto, &self.configuration, buf)
                 {
+                    self.apply_join_accept(&join_accept);
                     self.state = State::Joined(session);
                     Response::JoinSuccess
                 } else {
@@ -231,25 +467,58 @@ impl Mac {
                 }
             }
             State::Unjoined => Response::NoUpdate,
+        };
+        if matches!(response, Response::NoUpdate) {
+            self.configuration.last_rx_quality = previous_quality;
         }
+        response
+    }
+
+    /// Applies the network settings of a JoinAccept: the RX1 delay, the RX1 data rate offset and
+    /// the RX2 data rate of its DLSettings, and the channels of its CFList.
+    pub(crate) fn apply_join_accept<T: AsRef<[u8]>, C>(
+        &mut self,
+        join_accept: &DecryptedJoinAcceptPayload<T, C>,
+    ) -> JoinAcceptChanges {
+        let rx1_delay = del_to_delay_ms(join_accept.rx_delay());
+        let dl_settings = join_accept.dl_settings();
+        let changes = JoinAcceptChanges {
+            rx1_delay: self.configuration.rx1_delay != rx1_delay,
+            dl_settings: self
+                .region
+                .set_dl_settings(dl_settings.rx1_dr_offset(), dl_settings.rx2_data_rate()),
+            channels: self.region.process_join_accept(join_accept),
+        };
+        self.configuration.rx1_delay = rx1_delay;
+        changes
     }
 
     /// Handles a received RF frame during RXC window. Returns None if unparseable, fails decryption,
     /// or fails MIC verification. Upon successful data rx, provides Response::DownlinkReceived.
-    /// User must later call `take_downlink()` on the device to get the application data.
-    pub(crate) fn handle_rxc<C: CryptoFactory + Default, const N: usize, const D: usize>(
+    /// User must later call `take_downlink()` on the device to get the application data. The
+    /// `quality` of the frame is only recorded if it is accepted.
+    pub(crate) fn handle_rxc<C: CryptoFactory, const N: usize, const D: usize>(
         &mut self,
+        crypto: &C,
         buf: &mut RadioBuffer<N>,
+        quality: RxQuality,
         dl: &mut Vec<Downlink, D>,
     ) -> Result<Response> {
         match &mut self.state {
-            State::Joined(ref mut session) => Ok(session.handle_rx::<C, N, D>(
-                &mut self.region,
-                &mut self.configuration,
-                buf,
-                dl,
-                true,
-            )),
+            State::Joined(ref mut session) => {
+                let response = session.handle_rx::<C, N, D>(
+                    crypto,
+                    &mut self.region,
+                    &mut self.configuration,
+                    buf,
+                    dl,
+                    true,
+                );
+                if !matches!(response, Response::NoUpdate) {
+                    self.configuration.last_rx_quality = Some(quality);
+                }
+                Ok(response)
+            }
             State::Otaa(_) => Err(Error::NotJoined),
             State::Unjoined => Err(Error::NotJoined),
         }
@@ -279,6 +548,92 @@ impl Mac {
         }
     }
 
+    /// Queues a LinkCheckReq for the next uplink. Returns an error if the device is not joined.
+    pub(crate) fn request_link_check(&mut self) -> Result<()> {
+        match &mut self.state {
+            State::Joined(session) => {
+                session.uplink.request_link_check();
+                Ok(())
+            }
+            State::Otaa(_) | State::Unjoined => Err(Error::NotJoined),
+        }
+    }
+
+    pub(crate) fn take_link_check_ans(&mut self) -> Option<LinkCheckAns> {
+        self.configuration.link_check_ans.take()
+    }
+
+    /// Queues a DeviceTimeReq for the next uplink. Returns an error if the device is not joined.
+    pub(crate) fn request_device_time(&mut self) -> Result<()> {
+        match &mut self.state {
+            State::Joined(session) => {
+                session.uplink.request_device_time();
+                Ok(())
+            }
+            State::Otaa(_) | State::Unjoined => Err(Error::NotJoined),
+        }
+    }
+
+    pub(crate) fn take_device_time_ans(&mut self) -> Option<DeviceTimeAns> {
+        self.configuration.device_time_ans.take()
+    }
+
+    /// Applies a single downlink MAC command as if it had been received in a downlink and returns
+    /// its answer, if any. The answer is not queued for the next uplink.
+    pub(crate) fn apply_mac_command(
+        &mut self,
+        cmd: DownlinkMacCommand<'_>,
+    ) -> Option<UplinkMacCommandCreator> {
+        let mut uplink = uplink::Uplink::default();
+        self.configuration.handle_downlink_mac(&mut self.region, &mut uplink, cmd);
+        uplink.take_first_ans()
+    }
+
+    pub(crate) fn set_uplink_dwell_time(&mut self, enabled: bool) {
+        self.configuration.uplink_dwell_time = enabled;
+    }
+
+    pub(crate) fn set_max_downlink_mac_commands(&mut self, max: usize) {
+        self.configuration.max_downlink_mac_commands = max;
+    }
+
+    /// Battery level reported in DevStatusAns: 0 for an external power source, 1 to 254 for the
+    /// battery level and 255 if it cannot be measured.
+    pub(crate) fn set_battery_level(&mut self, battery_level: u8) {
+        self.configuration.battery_level = battery_level;
+    }
+
+    pub(crate) fn last_rx_quality(&self) -> Option<RxQuality> {
+        self.configuration.last_rx_quality
+    }
+
+    pub(crate) fn set_expected_net_id(&mut self, net_id: Option<u32>) {
+        self.configuration.expected_net_id = net_id;
+    }
+
+    pub(crate) fn devaddr_matches_net_id(&self) -> Option<bool> {
+        match &self.state {
+            State::Joined(session) => self.configuration.devaddr_matches_net_id(session.devaddr()),
+            State::Otaa(_) => None,
+            State::Unjoined => None,
+        }
+    }
+
+    pub(crate) fn set_mic_failure_threshold(&mut self, threshold: Option<u16>) {
+        self.configuration.mic_failure_threshold = threshold;
+    }
+
+    pub(crate) fn take_mac_event(&mut self) -> Option<MacEvent> {
+        if core::mem::take(&mut self.configuration.rejoin_recommended) {
+            Some(MacEvent::RejoinRecommended)
+        } else {
+            match core::mem::take(&mut self.configuration.dropped_mac_commands) {
+                0 => None,
+                dropped => Some(MacEvent::MacCommandsDropped(dropped)),
+            }
+        }
+    }
+
     pub(crate) fn is_joined(&self) -> bool {
         matches!(&self.state, State::Joined(_))
     }
@@ -291,6 +646,23 @@ impl Mac {
         }
     }
 
+    /// Maximum application payload which fits into the next uplink, given the current data rate
+    /// and the MAC commands which are pending to be sent in FOpts.
+    pub(crate) fn available_app_payload(&self) -> usize {
+        // MACPayload = FHDR (DevAddr + FCtrl + FCnt + FOpts) | FPort | FRMPayload
+        const FHDR_AND_FPORT_LEN: usize = 7 + 1;
+        let max_payload = usize::from(self.region.get_max_payload_length(
+            self.configuration.data_rate,
+            false,
+            self.configuration.uplink_dwell_time,
+        ));
+        let fopts_len = match &self.state {
+            State::Joined(session) => session.uplink.fopts_len(),
+            State::Otaa(_) | State::Unjoined => 0,
+        };
+        max_payload.saturating_sub(FHDR_AND_FPORT_LEN + fopts_len)
+    }
+
     pub(crate) fn get_rx_config(&self, buffer_ms: u32, frame: &Frame, window: &Window) -> RxConfig {
         RxConfig {
             rf: self.region.get_rx_config(self.configuration.data_rate, frame, window),
@@ -367,3 +739,155 @@ fn del_to_delay_ms(del: u8) -> u32 {
         _ => region::constants::RECEIVE_DELAY1,
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use lora_modulation::SpreadingFactor;
+    use lorawan::{creator::JoinAcceptCreator, default_crypto::DefaultFactory, keys::AppKey};
+
+    fn apply(mac: &mut Mac, dl_settings: u8, rx_delay: u8) -> JoinAcceptChanges {
+        let key = AppKey::from([1; 16]);
+        let mut buffer = [0u8; 17];
+        let mut phy = JoinAcceptCreator::new(&mut buffer[..]).unwrap();
+        phy.set_app_nonce(&[1; 3])
+            .set_net_id(&[1; 3])
+            .set_dev_addr(&[1, 2, 3, 4])
+            .set_dl_settings(dl_settings)
+            .set_rx_delay(rx_delay);
+        phy.build(&key, &DefaultFactory).unwrap();
+        let join_accept =
+            DecryptedJoinAcceptPayload::new_with_factory(&mut buffer[..], &key, DefaultFactory)
+                .unwrap();
+        mac.apply_join_accept(&join_accept)
+    }
+
+    fn rx_sf(mac: &Mac, datarate: region::DR, frame: Frame, window: Window) -> SpreadingFactor {
+        mac.region.get_rx_config(datarate, &frame, &window).bb.sf
+    }
+
+    #[test]
+    #[cfg(feature = "region-eu868")]
+    fn test_apply_join_accept_dl_settings() {
+        let mut mac = Mac::new(region::Configuration::new(region::Region::EU868), 14, 2);
+        // RX1DROffset 2, RX2DR 3, RxDelay 3 s
+        let changes = apply(&mut mac, 0x23, 3);
+        assert_eq!(
+            changes,
+            JoinAcceptChanges { rx1_delay: true, dl_settings: true, channels: false }
+        );
+        assert_eq!(mac.configuration.rx1_delay, 3000);
+        // DR5 lowered by 2 is DR3
+        assert_eq!(rx_sf(&mac, region::DR::_5, Frame::Data, Window::_1), SpreadingFactor::_9);
+        assert_eq!(rx_sf(&mac, region::DR::_5, Frame::Data, Window::_2), SpreadingFactor::_9);
+        // a JoinAccept is still expected with the default settings
+        assert_eq!(rx_sf(&mac, region::DR::_5, Frame::Join, Window::_1), SpreadingFactor::_7);
+        assert_eq!(rx_sf(&mac, region::DR::_5, Frame::Join, Window::_2), SpreadingFactor::_12);
+        // applying the same settings again changes nothing
+        assert_eq!(apply(&mut mac, 0x23, 3), JoinAcceptChanges::default());
+    }
+
+    #[test]
+    #[cfg(feature = "region-us915")]
+    fn test_apply_join_accept_dl_settings_fixed_channel_plan() {
+        let mut mac = Mac::new(region::Configuration::new(region::Region::US915), 30, 2);
+        // RX1DROffset 1, RX2DR 10, default RxDelay
+        let changes = apply(&mut mac, 0x1a, 0);
+        assert_eq!(
+            changes,
+            JoinAcceptChanges { rx1_delay: false, dl_settings: true, channels: false }
+        );
+        // DR0 maps to DR10, lowered by 1 to DR9
+        assert_eq!(rx_sf(&mac, region::DR::_0, Frame::Data, Window::_1), SpreadingFactor::_11);
+        assert_eq!(rx_sf(&mac, region::DR::_0, Frame::Data, Window::_2), SpreadingFactor::_10);
+        assert_eq!(rx_sf(&mac, region::DR::_0, Frame::Join, Window::_2), SpreadingFactor::_12);
+    }
+
+    fn join_dev_nonce(mac: &mut Mac) -> Result<u16> {
+        let mut buf: RadioBuffer<255> = RadioBuffer::new();
+        let credentials = NetworkCredentials::new(
+            crate::AppEui::from([0; 8]),
+            crate::DevEui::from([0; 8]),
+            AppKey::from([1; 16]),
+        );
+        mac.join_otaa::<DefaultFactory, _, 255>(
+            &DefaultFactory,
+            &mut rand::rngs::OsRng,
+            credentials,
+            &mut buf,
+        )?;
+        let join_request = lorawan::parser::JoinRequestPayload::new(buf.as_ref_for_read()).unwrap();
+        Ok(u16::from(join_request.dev_nonce().to_owned()))
+    }
+
+    #[test]
+    #[cfg(feature = "region-eu868")]
+    fn test_join_dev_nonce_counter() {
+        let mut mac = Mac::new(region::Configuration::new(region::Region::EU868), 14, 2);
+        mac.set_dev_nonce_counter(Some(41));
+        assert!(matches!(join_dev_nonce(&mut mac), Ok(41)));
+        assert!(matches!(join_dev_nonce(&mut mac), Ok(42)));
+        assert_eq!(mac.dev_nonce_counter(), Some(43));
+
+        mac.set_dev_nonce_counter(Some(0xffff));
+        assert!(matches!(join_dev_nonce(&mut mac), Ok(0xffff)));
+        // the counter must not wrap around, as the network would reject reused DevNonces
+        assert!(matches!(join_dev_nonce(&mut mac), Err(Error::DevNonceExhausted)));
+        assert!(matches!(join_dev_nonce(&mut mac), Err(Error::DevNonceExhausted)));
+    }
+
+    #[test]
+    fn test_dev_status_ans_deferred_when_fopts_full() {
+        let mut uplink = uplink::Uplink::default();
+        // 7 LinkADRAns take 14 of the 15 bytes of FOpts
+        uplink.ack_link_adr(7, true);
+        uplink.ack_dev_status(100, -40);
+        let cmds = uplink.take_f_opts();
+        assert_eq!(cmds.len(), 7);
+        assert!(cmds.iter().all(|cmd| matches!(cmd, UplinkMacCommandCreator::LinkADRAns(_))));
+        assert_eq!(uplink.fopts_len(), 3);
+        let cmds = uplink.take_f_opts();
+        match cmds.as_slice() {
+            [UplinkMacCommandCreator::DevStatusAns(ans)] => {
+                // the margin is clamped to the 6-bit signed range
+                assert_eq!(ans.build(), &[0x06, 100, 0x20]);
+            }
+            cmds => panic!("Unexpected commands: {cmds:?}"),
+        }
+        assert_eq!(uplink.fopts_len(), 0);
+    }
+
+    #[test]
+    #[cfg(feature = "region-us915")]
+    fn test_link_adr_ans_over_fopts_deferred() {
+        let mut mac = Mac::new(region::Configuration::new(region::Region::US915), 30, 2);
+        let mut uplink = uplink::Uplink::default();
+        let mut payload = [0u8; 4];
+        payload[3] = 0x70;
+        let cmds = core::iter::repeat_with(|| {
+            DownlinkMacCommand::LinkADRReq(LinkADRReqPayload::new(&payload).unwrap())
+        })
+        .take(10);
+        mac.configuration.handle_downlink_macs(&mut mac.region, &mut uplink, cmds);
+        // each LinkADRAns takes 2 bytes, so 7 of them fill FOpts
+        assert_eq!(uplink.take_f_opts().len(), 7);
+        assert_eq!(uplink.take_f_opts().len(), 3);
+        assert!(uplink.take_f_opts().is_empty());
+        assert_eq!(mac.take_mac_event(), None);
+    }
+
+    #[test]
+    #[cfg(feature = "region-us915")]
+    fn test_devaddr_matches_net_id_of_type_3() {
+        let mut mac = Mac::new(region::Configuration::new(region::Region::US915), 30, 2);
+        // type 3 DevAddr with NwkID 0x16
+        let devaddr = DevAddr::from(0xe02c0001);
+        mac.configuration.expected_net_id = Some(0x600016);
+        assert_eq!(mac.configuration.devaddr_matches_net_id(&devaddr), Some(true));
+        // same NwkID but in a type 0 NetID
+        mac.configuration.expected_net_id = Some(0x000016);
+        assert_eq!(mac.configuration.devaddr_matches_net_id(&devaddr), Some(false));
+        mac.configuration.expected_net_id = Some(0x600017);
+        assert_eq!(mac.configuration.devaddr_matches_net_id(&devaddr), Some(false));
+    }
+}
@@ -1,13 +1,11 @@
-use super::{del_to_delay_ms, session::Session, Response};
+use super::{session::Session, Response};
 use crate::radio::RadioBuffer;
-use crate::region::Configuration;
 use crate::{AppEui, AppKey, DevEui};
 use lorawan::keys::CryptoFactory;
 use lorawan::{
     creator::JoinRequestCreator,
     parser::{parse_with_factory as lorawan_parse, *},
 };
-use rand_core::RngCore;
 
 pub(crate) type DevNonce = lorawan::parser::DevNonce<[u8; 2]>;
 
@@ -30,41 +28,43 @@ impl Otaa {
 
     /// Prepare a join request to be sent. This populates the radio buffer with the request to be
     /// sent, and returns the radio config to use for transmitting.
-    pub(crate) fn prepare_buffer<C: CryptoFactory + Default, G: RngCore, const N: usize>(
+    pub(crate) fn prepare_buffer<C: CryptoFactory, const N: usize>(
         &mut self,
-        rng: &mut G,
+        crypto: &C,
+        dev_nonce: DevNonce,
         buf: &mut RadioBuffer<N>,
     ) -> u16 {
-        self.dev_nonce = DevNonce::from(rng.next_u32() as u16);
+        self.dev_nonce = dev_nonce;
         buf.clear();
         let mut phy = JoinRequestCreator::new(buf.as_mut()).unwrap();
         phy.set_app_eui(self.network_credentials.appeui)
             .set_dev_eui(self.network_credentials.deveui)
             .set_dev_nonce(self.dev_nonce);
-        let crypto_factory = C::default();
-        let len = phy.build(&self.network_credentials.appkey, &crypto_factory).len();
+        let len = phy.build(&self.network_credentials.appkey, crypto).len();
         buf.set_pos(len);
         u16::from(self.dev_nonce)
     }
 
-    pub(crate) fn handle_rx<C: CryptoFactory + Default, const N: usize>(
+    /// Handles a received JoinAccept, returning the new session along with the decrypted
+    /// JoinAccept so that its settings can be applied.
+    pub(crate) fn handle_rx<'a, 'c, C: CryptoFactory, const N: usize>(
         &mut self,
-        region: &mut Configuration,
-        configuration: &mut super::Configuration,
-        rx: &mut RadioBuffer<N>,
-    ) -> Option<Session> {
+        crypto: &'c C,
+        configuration: &super::Configuration,
+        rx: &'a mut RadioBuffer<N>,
+    ) -> Option<(Session, DecryptedJoinAcceptPayload<&'a mut [u8], &'c C>)> {
         if let Ok(PhyPayload::JoinAccept(JoinAcceptPayload::Encrypted(encrypted))) =
-            lorawan_parse(rx.as_mut_for_read(), C::default())
+            lorawan_parse(rx.as_mut_for_read(), crypto)
         {
             let decrypt = encrypted.decrypt(&self.network_credentials.appkey);
-            region.process_join_accept(&decrypt);
-            configuration.rx1_delay = del_to_delay_ms(decrypt.rx_delay());
             if decrypt.validate_mic(&self.network_credentials.appkey) {
-                return Some(Session::derive_new(
-                    &decrypt,
-                    self.dev_nonce,
-                    &self.network_credentials,
-                ));
+                let session =
+                    Session::derive_new(&decrypt, self.dev_nonce, &self.network_credentials);
+                // A mismatch does not fail the join, as the device could be roaming.
+                if configuration.devaddr_matches_net_id(session.devaddr()) == Some(false) {
+                    warn!("DevAddr {} does not belong to the expected NetID.", session.devaddr());
+                }
+                return Some((session, decrypt));
             }
         }
         None
@@ -0,0 +1,113 @@
+//! Class B beacon and ping slot timing helpers.
+//!
+//! Unless stated otherwise, all times are in milliseconds and relative to the same (arbitrary)
+//! epoch.
+
+/// Interval between two beacons.
+pub const BEACON_PERIOD_MS: u64 = 128_000;
+/// Interval between two beacons, in seconds.
+pub const BEACON_PERIOD_S: u32 = 128;
+/// Time reserved at the start of each beacon period for the beacon itself.
+pub const BEACON_RESERVED_MS: u64 = 2_120;
+/// Time at the end of each beacon period during which no ping slot is opened, so that the next
+/// beacon can be received.
+pub const BEACON_GUARD_MS: u64 = 3_000;
+/// Time between the end of `BEACON_RESERVED_MS` and the start of `BEACON_GUARD_MS`, during
+/// which the ping slots are opened.
+pub const BEACON_WINDOW_MS: u64 = 122_880;
+/// Length of a single ping slot.
+pub const SLOT_LEN_MS: u64 = 30;
+/// Number of ping slots in the beacon window (2^12).
+const BEACON_WINDOW_SLOTS: u64 = 4096;
+
+/// Computes the delay in milliseconds until the start of the next ping slot.
+///
+/// # Argument
+///
+/// * now - the current time.
+/// * beacon_time - the start of the last received beacon. Later beacon periods are accounted for
+///   if `now` is more than one beacon period ahead.
+/// * ping_offset - the pseudo-random ping offset for the current beacon period, in slots.
+/// * periodicity - the ping slot periodicity (0 to 7), ie: `pingPeriod = 2^(5 + periodicity)`.
+///
+/// Returns `None` if `periodicity` is out of range or `ping_offset` is not within the ping period.
+/// A ping slot starting exactly at `now` gives a delay of 0.
+pub fn next_slot_in(now: u64, beacon_time: u64, ping_offset: u16, periodicity: u8) -> Option<u32> {
+    if periodicity > 7 {
+        return None;
+    }
+    let ping_period = 1u64 << (5 + periodicity);
+    let ping_offset = u64::from(ping_offset);
+    if ping_offset >= ping_period {
+        return None;
+    }
+    let ping_nb = BEACON_WINDOW_SLOTS / ping_period;
+    let first_slot = BEACON_RESERVED_MS + ping_offset * SLOT_LEN_MS;
+    let period_len = ping_period * SLOT_LEN_MS;
+
+    let elapsed = now.saturating_sub(beacon_time) % BEACON_PERIOD_MS;
+    let n = elapsed.saturating_sub(first_slot).div_ceil(period_len);
+    let next_slot = if n < ping_nb {
+        first_slot + n * period_len
+    } else {
+        // all the slots of this beacon period are gone, wrap to the next one
+        BEACON_PERIOD_MS + first_slot
+    };
+    Some((next_slot - elapsed) as u32)
+}
+
+/// Computes the GPS time in seconds of the next beacon, which are sent every `BEACON_PERIOD_S`
+/// seconds since the GPS epoch. A beacon sent exactly at `now_gps` is the next one.
+///
+/// Like the time carried by beacons, it is the GPS time modulo 2^32, so it wraps to 0 after
+/// `u32::MAX`.
+pub fn next_beacon_time(now_gps: u32) -> u32 {
+    now_gps.wrapping_add((BEACON_PERIOD_S - now_gps % BEACON_PERIOD_S) % BEACON_PERIOD_S)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_next_slot_in() {
+        // periodicity 0: a slot every 32 slots (960 ms), starting at 2120 + 10 * 30 = 2420 ms
+        assert_eq!(next_slot_in(0, 0, 10, 0), Some(2420));
+        assert_eq!(next_slot_in(2420, 0, 10, 0), Some(0));
+        assert_eq!(next_slot_in(3000, 0, 10, 0), Some(380));
+        // the same, one beacon period later
+        assert_eq!(next_slot_in(1_000 + BEACON_PERIOD_MS + 3000, 1_000, 10, 0), Some(380));
+    }
+
+    #[test]
+    fn test_next_slot_in_wraps_to_next_beacon_period() {
+        // periodicity 7: a single slot per beacon period at 2120 + 100 * 30 = 5120 ms
+        assert_eq!(next_slot_in(6000, 0, 100, 7), Some(127_120));
+        // the last slot with periodicity 0 starts at 2420 + 127 * 960 = 124340 ms
+        assert_eq!(next_slot_in(124_341, 0, 10, 0), Some(128_000 - 124_341 + 2420));
+    }
+
+    #[test]
+    fn test_beacon_period_layout() {
+        assert_eq!(BEACON_RESERVED_MS + BEACON_WINDOW_MS + BEACON_GUARD_MS, BEACON_PERIOD_MS);
+        assert_eq!(BEACON_WINDOW_SLOTS * SLOT_LEN_MS, BEACON_WINDOW_MS);
+    }
+
+    #[test]
+    fn test_next_beacon_time() {
+        // 1_400_000_000 = 10_937_500 * 128
+        assert_eq!(next_beacon_time(1_400_000_000), 1_400_000_000);
+        assert_eq!(next_beacon_time(1_400_000_001), 1_400_000_128);
+        assert_eq!(next_beacon_time(1_400_000_127), 1_400_000_128);
+        assert_eq!(next_beacon_time(0), 0);
+        // 2^32 is a multiple of the beacon period, the next beacon is at 0 again
+        assert_eq!(next_beacon_time(u32::MAX), 0);
+        assert_eq!(next_beacon_time(u32::MAX - 127), u32::MAX - 127);
+    }
+
+    #[test]
+    fn test_next_slot_in_invalid() {
+        assert_eq!(next_slot_in(0, 0, 0, 8), None);
+        assert_eq!(next_slot_in(0, 0, 32, 0), None);
+    }
+}
@@ -5,6 +5,7 @@ use lorawan::maccommands::{DownlinkMacCommand, MacCommandIterator};
 use lorawan::{
     creator::DataPayloadCreator,
     maccommands::SerializableMacCommand,
+    packet_length::phy::mac::fhdr::FOPTS_MAX_LEN,
     parser::{parse_with_factory as lorawan_parse, *},
     parser::{DecryptedJoinAcceptPayload, DevAddr},
 };
@@ -27,6 +28,13 @@ pub struct Session {
     pub devaddr: DevAddr<[u8; 4]>,
     pub fcnt_up: u32,
     pub fcnt_down: u32,
+    /// Whether a downlink has been accepted in this session. Needed to tell a first downlink with
+    /// FCntDown 0 apart from a repeated one.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub downlink_received: bool,
+    /// Number of consecutive downlinks addressed to this session which failed the MIC check.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub mic_failures: u16,
 }
 
 #[derive(Clone, Debug)]
@@ -70,10 +78,21 @@ impl Session {
             confirmed: false,
             fcnt_down: 0,
             fcnt_up: 0,
+            downlink_received: false,
+            mic_failures: 0,
             uplink: uplink::Uplink::default(),
         }
     }
 
+    pub(crate) fn rekey(&mut self, nwkskey: NwkSKey, appskey: AppSKey) {
+        self.nwkskey = nwkskey;
+        self.appskey = appskey;
+        self.fcnt_up = 0;
+        self.fcnt_down = 0;
+        self.downlink_received = false;
+        self.mic_failures = 0;
+    }
+
     pub fn devaddr(&self) -> &DevAddr<[u8; 4]> {
         &self.devaddr
     }
@@ -95,8 +114,9 @@ impl Session {
 }
 
 impl Session {
-    pub(crate) fn handle_rx<C: CryptoFactory + Default, const N: usize, const D: usize>(
+    pub(crate) fn handle_rx<C: CryptoFactory, const N: usize, const D: usize>(
         &mut self,
+        crypto: &C,
         region: &mut region::Configuration,
         configuration: &mut super::Configuration,
         rx: &mut RadioBuffer<N>,
@@ -104,64 +124,73 @@ impl Session {
         ignore_mac: bool,
     ) -> Response {
         if let Ok(PhyPayload::Data(DataPayload::Encrypted(encrypted_data))) =
-            lorawan_parse(rx.as_mut_for_read(), C::default())
+            lorawan_parse(rx.as_mut_for_read(), crypto)
         {
             if self.devaddr() == &encrypted_data.fhdr().dev_addr() {
-                let fcnt = encrypted_data.fhdr().fcnt() as u32;
-                let confirmed = encrypted_data.is_confirmed();
-                if encrypted_data.validate_mic(self.nwkskey().inner(), fcnt)
-                    && (fcnt > self.fcnt_down || fcnt == 0)
-                {
-                    self.fcnt_down = fcnt;
-                    // We can safely unwrap here because we already validated the MIC
-                    let decrypted = encrypted_data
-                        .decrypt(
-                            Some(self.nwkskey().inner()),
-                            Some(self.appskey().inner()),
-                            self.fcnt_down,
-                        )
-                        .unwrap();
-
-                    if !ignore_mac {
-                        // MAC commands may be in the FHDR or the FRMPayload
-                        configuration.handle_downlink_macs(
-                            region,
-                            &mut self.uplink,
-                            MacCommandIterator::<DownlinkMacCommand<'_>>::new(
-                                decrypted.fhdr().data(),
-                            ),
-                        );
-                        if let FRMPayload::MACCommands(mac_cmds) = decrypted.frm_payload() {
-                            configuration.handle_downlink_macs(
-                                region,
-                                &mut self.uplink,
-                                MacCommandIterator::<DownlinkMacCommand<'_>>::new(mac_cmds.data()),
-                            );
-                        }
-                    }
-
-                    if confirmed {
-                        self.uplink.set_downlink_confirmation();
+                // A repeated FCntDown is a retransmission which was already processed, so it is
+                // ignored and the RX window is kept open, as is any older or too distant one.
+                let fcnt = match reconstruct_fcnt(self.fcnt_down, encrypted_data.fhdr().fcnt()) {
+                    Ok(fcnt) if fcnt > self.fcnt_down || (fcnt == 0 && !self.downlink_received) => {
+                        fcnt
                     }
+                    _ => return Response::NoUpdate,
+                };
+                let confirmed = encrypted_data.is_confirmed();
+                if !encrypted_data.validate_mic(self.nwkskey().inner(), fcnt) {
+                    self.mic_failures = self.mic_failures.saturating_add(1);
+                    configuration.record_mic_failures(self.mic_failures);
+                    return Response::NoUpdate;
+                }
+                self.mic_failures = 0;
+                self.fcnt_down = fcnt;
+                self.downlink_received = true;
+                // We can safely unwrap here because we already validated the MIC
+                let decrypted = encrypted_data
+                    .decrypt(
+                        Some(self.nwkskey().inner()),
+                        Some(self.appskey().inner()),
+                        self.fcnt_down,
+                    )
+                    .unwrap();
 
-                    return if self.fcnt_up == 0xFFFF_FFFF {
-                        // if the FCnt is used up, the session has expired
-                        Response::SessionExpired
-                    } else {
-                        // we can always increment fcnt_up when we receive a downlink
-                        self.fcnt_up += 1;
-                        if let (Some(fport), FRMPayload::Data(data)) =
-                            (decrypted.f_port(), decrypted.frm_payload())
-                        {
-                            // heapless Vec from slice fails only if slice is too large.
-                            // A data FRM payload will never exceed 256 bytes.
-                            let data = Vec::from_slice(data).unwrap();
-                            // TODO: propagate error type when heapless vec is full?
-                            let _ = dl.push(Downlink { data, fport });
-                        }
-                        Response::DownlinkReceived(fcnt)
+                if !ignore_mac {
+                    // MAC commands may be in the FHDR or the FRMPayload
+                    let frm_payload = decrypted.frm_payload();
+                    let frm_payload_cmds = match &frm_payload {
+                        FRMPayload::MACCommands(mac_cmds) => mac_cmds.data(),
+                        _ => &[],
                     };
+                    configuration.handle_downlink_macs(
+                        region,
+                        &mut self.uplink,
+                        MacCommandIterator::<DownlinkMacCommand<'_>>::new(decrypted.fhdr().data())
+                            .chain(MacCommandIterator::<DownlinkMacCommand<'_>>::new(
+                                frm_payload_cmds,
+                            )),
+                    );
+                }
+
+                if confirmed {
+                    self.uplink.set_downlink_confirmation();
                 }
+
+                return if self.fcnt_up == 0xFFFF_FFFF {
+                    // if the FCnt is used up, the session has expired
+                    Response::SessionExpired
+                } else {
+                    // we can always increment fcnt_up when we receive a downlink
+                    self.fcnt_up += 1;
+                    if let (Some(fport), FRMPayload::Data(data)) =
+                        (decrypted.f_port(), decrypted.frm_payload())
+                    {
+                        // heapless Vec from slice fails only if slice is too large.
+                        // A data FRM payload will never exceed 256 bytes.
+                        let data = Vec::from_slice(data).unwrap();
+                        // TODO: propagate error type when heapless vec is full?
+                        let _ = dl.push(Downlink { data, fport });
+                    }
+                    Response::DownlinkReceived(fcnt)
+                };
             }
         }
         Response::NoUpdate
@@ -182,8 +211,9 @@ impl Session {
         }
     }
 
-    pub(crate) fn prepare_buffer<C: CryptoFactory + Default, const N: usize>(
+    pub(crate) fn prepare_buffer<C: CryptoFactory, const N: usize>(
         &mut self,
+        crypto: &C,
         data: &SendData<'_>,
         tx_buffer: &mut RadioBuffer<N>,
     ) -> FcntUp {
@@ -206,24 +236,12 @@ impl Session {
             .set_dev_addr(self.devaddr)
             .set_fcnt(fcnt);
 
-        let mut cmds = Vec::new();
-        self.uplink.get_cmds(&mut cmds);
-        let mut dyn_cmds: Vec<&dyn SerializableMacCommand, 8> = Vec::new();
-
-        for cmd in &cmds {
-            if let Err(_e) = dyn_cmds.push(cmd) {
-                panic!("dyn_cmds too small compared to cmds")
-            }
-        }
+        // answers which do not fit into the FOpts are left pending for the next uplink
+        let cmds = self.uplink.take_f_opts();
+        let dyn_cmds: Vec<&dyn SerializableMacCommand, FOPTS_MAX_LEN> =
+            cmds.iter().map(|cmd| cmd as &dyn SerializableMacCommand).collect();
 
-        let crypto_factory = C::default();
-        match phy.build(
-            data.data,
-            dyn_cmds.as_slice(),
-            &self.nwkskey,
-            &self.appskey,
-            &crypto_factory,
-        ) {
+        match phy.build(data.data, dyn_cmds.as_slice(), &self.nwkskey, &self.appskey, crypto) {
             Ok(packet) => {
                 tx_buffer.clear();
                 tx_buffer.extend_from_slice(packet).unwrap();
@@ -233,3 +251,55 @@ impl Session {
         fcnt
     }
 }
+
+#[cfg(all(test, feature = "serde"))]
+mod test {
+    use super::*;
+    use lorawan::maccommands::UplinkMacCommandCreator;
+
+    /// `Session` as serialized before the pending MAC command answers were added to `Uplink`.
+    #[derive(serde::Serialize)]
+    struct PreviousSession {
+        uplink: PreviousUplink,
+        confirmed: bool,
+        nwkskey: [u8; 16],
+        appskey: [u8; 16],
+        devaddr: [u8; 4],
+        fcnt_up: u32,
+        fcnt_down: u32,
+    }
+
+    #[derive(serde::Serialize)]
+    struct PreviousUplink {
+        adr_ans: u8,
+        rx_delay_ans: bool,
+        confirmed: bool,
+    }
+
+    #[test]
+    fn test_deserialize_previous_session() {
+        let previous = PreviousSession {
+            uplink: PreviousUplink { adr_ans: 1, rx_delay_ans: false, confirmed: false },
+            confirmed: false,
+            nwkskey: [1; 16],
+            appskey: [2; 16],
+            devaddr: [0x26, 0x01, 0x1b, 0xda],
+            fcnt_up: 7,
+            fcnt_down: 3,
+        };
+        let mut buf = std::vec::Vec::new();
+        ciborium::into_writer(&previous, &mut buf).unwrap();
+
+        let mut session: Session = ciborium::from_reader(&buf[..]).unwrap();
+        assert_eq!(session.nwkskey, NwkSKey::from([1; 16]));
+        assert_eq!(session.devaddr, DevAddr::from([0x26, 0x01, 0x1b, 0xda]));
+        assert_eq!((session.fcnt_up, session.fcnt_down), (7, 3));
+        assert!(!session.downlink_received);
+        // the pending LinkADRAns still acknowledges everything
+        let ans = session.uplink.take_first_ans();
+        assert!(
+            matches!(ans, Some(UplinkMacCommandCreator::LinkADRAns(ans)) if ans.build() == [0x03, 0x07])
+        );
+        assert!(session.uplink.take_first_ans().is_none());
+    }
+}
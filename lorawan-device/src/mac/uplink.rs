@@ -3,23 +3,49 @@ This a temporary design where flags will be left about desired MAC uplinks by th
 During Uplink assembly, this struct will be inquired to drive construction
  */
 use heapless::Vec;
-use lorawan::maccommands::{LinkADRAnsPayload, RXTimingSetupAnsPayload, UplinkMacCommand};
+use lorawan::maccommandcreator::build_f_opts;
+use lorawan::maccommands::{
+    DevStatusAnsCreator, DeviceTimeReqCreator, LinkADRAnsCreator, LinkCheckReqCreator,
+    NewChannelAnsCreator, RXTimingSetupAnsCreator, SerializableMacCommand, UplinkMacCommandCreator,
+};
+use lorawan::packet_length::phy::mac::fhdr::FOPTS_MAX_LEN;
 
 #[derive(Default, Debug, Clone)]
 #[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Uplink {
     pub adr_ans: AdrAns,
+    // sessions saved before the status was tracked acknowledge everything, as was always done
+    #[cfg_attr(feature = "serde", serde(default = "adr_ans_status_ack"))]
+    adr_ans_status: u8,
     pub rx_delay_ans: RxDelayAns,
+    #[cfg_attr(feature = "serde", serde(default))]
+    dev_status_ans: DevStatusAns,
+    #[cfg_attr(feature = "serde", serde(default))]
+    new_channel_ans: NewChannelAns,
+    #[cfg_attr(feature = "serde", serde(default))]
+    link_check_req: bool,
+    #[cfg_attr(feature = "serde", serde(default))]
+    device_time_req: bool,
     confirmed: bool,
 }
 
+#[cfg(feature = "serde")]
+fn adr_ans_status_ack() -> u8 {
+    0b111
+}
+
 // multiple AdrAns may happen per downlink
 // so we aggregate how many AdrAns are required
 type AdrAns = u8;
 // only one RxDelayReq will happen
 // so we only need to implement this as a bool
 type RxDelayAns = bool;
+// battery level and demodulation margin to answer the last DevStatusReq with
+type DevStatusAns = Option<(u8, i8)>;
+// status of each NewChannelReq to answer, there are at most as many requests as configurable
+// channels; any further one is left unanswered
+type NewChannelAns = Vec<u8, 5>;
 
 //work around for E0390
 pub(crate) trait MacAnsTrait {
@@ -68,21 +94,126 @@ impl Uplink {
         self.confirmed
     }
 
+    /// Queues a LinkADRAns for each of `count` LinkADRReqs handled as a block.
+    pub fn ack_link_adr(&mut self, count: u8, channel_mask_ack: bool) {
+        // data rate and TX power are ignored, and thus always acknowledged
+        self.adr_ans_status = 0b110 | u8::from(channel_mask_ack);
+        self.adr_ans = self.adr_ans.saturating_add(count);
+    }
+
     pub fn ack_rx_delay(&mut self) {
         self.rx_delay_ans.add();
     }
 
-    pub fn get_cmds(&mut self, macs: &mut Vec<UplinkMacCommand<'_>, 8>) {
-        for _ in 0..self.adr_ans.get() {
-            macs.push(UplinkMacCommand::LinkADRAns(LinkADRAnsPayload::new(&[0x07]).unwrap()))
-                .unwrap();
+    pub fn request_link_check(&mut self) {
+        self.link_check_req = true;
+    }
+
+    pub fn request_device_time(&mut self) {
+        self.device_time_req = true;
+    }
+
+    pub fn ack_dev_status(&mut self, battery: u8, margin: i8) {
+        self.dev_status_ans = Some((battery, margin.clamp(-32, 31)));
+    }
+
+    /// Queues a NewChannelAns, returning `false` if too many are already pending.
+    pub fn ack_new_channel(
+        &mut self,
+        channel_frequency_ack: bool,
+        data_rate_range_ack: bool,
+    ) -> bool {
+        let status = u8::from(channel_frequency_ack) | u8::from(data_rate_range_ack) << 1;
+        // full only if the network sent more NewChannelReq than there are channels
+        self.new_channel_ans.push(status).is_ok()
+    }
+
+    /// Takes the first pending answer, in the order they are added to an uplink. Used for
+    /// handling MAC commands one at a time.
+    pub fn take_first_ans(&mut self) -> Option<UplinkMacCommandCreator> {
+        match self.first_cmd() {
+            Some(
+                UplinkMacCommandCreator::LinkCheckReq(_)
+                | UplinkMacCommandCreator::DeviceTimeReq(_),
+            )
+            | None => None,
+            cmd => {
+                self.remove_first_cmd();
+                cmd
+            }
         }
-        self.adr_ans.clear();
+    }
+
+    /// Takes the pending MAC commands which fit into the FOpts of the next uplink, in the order
+    /// they are added. The others are left pending for a later uplink.
+    pub fn take_f_opts(&mut self) -> Vec<UplinkMacCommandCreator, FOPTS_MAX_LEN> {
+        let mut f_opts = [0; FOPTS_MAX_LEN];
+        let mut len = 0;
+        let mut cmds = Vec::new();
+        while let Some(cmd) = self.first_cmd() {
+            match build_f_opts(&[&cmd], &mut f_opts[len..]) {
+                Ok(cmd_len) => len += cmd_len,
+                Err(_) => break,
+            }
+            self.remove_first_cmd();
+            // every command takes at least one byte, so there are never more than FOPTS_MAX_LEN
+            let _ = cmds.push(cmd);
+        }
+        cmds
+    }
+
+    /// Number of bytes the pending MAC commands will occupy in the FOpts of the next uplink.
+    pub fn fopts_len(&self) -> usize {
+        self.clone().take_f_opts().iter().map(|cmd| 1 + cmd.payload_len()).sum()
+    }
 
-        if self.rx_delay_ans.get() != 0 {
-            macs.push(UplinkMacCommand::RXTimingSetupAns(RXTimingSetupAnsPayload::new(&[])))
-                .unwrap();
+    fn first_cmd(&self) -> Option<UplinkMacCommandCreator> {
+        if self.adr_ans.get() != 0 {
+            let mut creator = LinkADRAnsCreator::new();
+            creator
+                .set_channel_mask_ack(self.adr_ans_status & 0x01 != 0)
+                .set_data_rate_ack(true)
+                .set_tx_power_ack(true);
+            Some(UplinkMacCommandCreator::LinkADRAns(creator))
+        } else if self.rx_delay_ans.get() != 0 {
+            Some(UplinkMacCommandCreator::RXTimingSetupAns(RXTimingSetupAnsCreator::new()))
+        } else if let Some((battery, margin)) = self.dev_status_ans {
+            let mut creator = DevStatusAnsCreator::new();
+            // margin was clamped to a valid range when queueing
+            creator.set_battery(battery).set_margin(margin).unwrap();
+            Some(UplinkMacCommandCreator::DevStatusAns(creator))
+        } else if let Some(status) = self.new_channel_ans.first() {
+            Some(UplinkMacCommandCreator::NewChannelAns(new_channel_ans(*status)))
+        } else if self.link_check_req {
+            Some(UplinkMacCommandCreator::LinkCheckReq(LinkCheckReqCreator::new()))
+        } else if self.device_time_req {
+            Some(UplinkMacCommandCreator::DeviceTimeReq(DeviceTimeReqCreator::new()))
+        } else {
+            None
         }
-        self.rx_delay_ans.clear();
     }
+
+    fn remove_first_cmd(&mut self) {
+        if self.adr_ans.get() != 0 {
+            self.adr_ans -= 1;
+        } else if self.rx_delay_ans.get() != 0 {
+            self.rx_delay_ans.clear();
+        } else if self.dev_status_ans.is_some() {
+            self.dev_status_ans = None;
+        } else if !self.new_channel_ans.is_empty() {
+            self.new_channel_ans.remove(0);
+        } else if self.link_check_req {
+            self.link_check_req = false;
+        } else {
+            self.device_time_req = false;
+        }
+    }
+}
+
+fn new_channel_ans(status: u8) -> NewChannelAnsCreator {
+    let mut creator = NewChannelAnsCreator::new();
+    creator
+        .set_channel_frequency_ack(status & 0x01 != 0)
+        .set_data_rate_range_ack(status & 0x02 != 0);
+    creator
 }
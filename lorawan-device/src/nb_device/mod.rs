@@ -3,7 +3,7 @@
 //! implementation.
 use super::radio::RadioBuffer;
 use super::*;
-use crate::nb_device::radio::PhyRxTx;
+use crate::nb_device::radio::{PhyRxTx, RxQuality};
 use mac::{Mac, SendData};
 
 pub(crate) mod state;
@@ -17,12 +17,12 @@ type TimestampMs = u32;
 pub struct Device<R, C, RNG, const N: usize, const D: usize = 1>
 where
     R: PhyRxTx + Timings,
-    C: CryptoFactory + Default,
+    C: CryptoFactory,
     RNG: RngCore,
 {
     state: State,
     shared: Shared<R, RNG, N, D>,
-    crypto: PhantomData<C>,
+    crypto: C,
 }
 
 impl<R, C, RNG, const N: usize, const D: usize> Device<R, C, RNG, N, D>
@@ -32,8 +32,26 @@ where
     RNG: RngCore,
 {
     pub fn new(region: region::Configuration, radio: R, rng: RNG) -> Device<R, C, RNG, N, D> {
+        Device::new_with_crypto(region, radio, rng, C::default())
+    }
+}
+
+impl<R, C, RNG, const N: usize, const D: usize> Device<R, C, RNG, N, D>
+where
+    R: PhyRxTx + Timings,
+    C: CryptoFactory,
+    RNG: RngCore,
+{
+    /// Create a new [`Device`] using the given crypto factory instance, eg: a
+    /// [`lorawan::default_crypto::Crypto`] selecting hardware or software AES at runtime.
+    pub fn new_with_crypto(
+        region: region::Configuration,
+        radio: R,
+        rng: RNG,
+        crypto: C,
+    ) -> Device<R, C, RNG, N, D> {
         Device {
-            crypto: PhantomData,
+            crypto,
             state: State::default(),
             shared: Shared {
                 radio,
@@ -57,6 +75,72 @@ where
         }
     }
 
+    /// Request a link check from the network with the next uplink. The answer can be retrieved
+    /// with [`Self::take_link_check_ans`] once a downlink was received.
+    pub fn request_link_check(&mut self) -> Result<(), Error<R>> {
+        Ok(self.shared.mac.request_link_check()?)
+    }
+
+    /// Take the answer to the last link check request, if one was received.
+    pub fn take_link_check_ans(&mut self) -> Option<mac::LinkCheckAns> {
+        self.shared.mac.take_link_check_ans()
+    }
+
+    /// Request the network time with the next uplink, eg: for keeping a Class B clock aligned. The
+    /// answer can be retrieved with [`Self::take_device_time_ans`] once a downlink was received.
+    pub fn request_device_time(&mut self) -> Result<(), Error<R>> {
+        Ok(self.shared.mac.request_device_time()?)
+    }
+
+    /// Take the answer to the last device time request, if one was received.
+    pub fn take_device_time_ans(&mut self) -> Option<mac::DeviceTimeAns> {
+        self.shared.mac.take_device_time_ans()
+    }
+
+    /// Apply a single downlink MAC command as if it had been received from the network and return
+    /// its answer, if any. The answer is not queued for the next uplink. Meant for testing and
+    /// debugging the handling of individual MAC commands.
+    pub fn apply_mac_command(
+        &mut self,
+        cmd: lorawan::maccommands::DownlinkMacCommand<'_>,
+    ) -> Option<lorawan::maccommands::UplinkMacCommandCreator> {
+        self.shared.mac.apply_mac_command(cmd)
+    }
+
+    /// Enable or disable uplink dwell time limitations (eg: AS923), which reduce the maximum
+    /// payload size. While enabled, sending a larger payload fails with
+    /// [`mac::Error::DwellTimeExceeded`].
+    pub fn set_uplink_dwell_time(&mut self, enabled: bool) {
+        self.shared.mac.set_uplink_dwell_time(enabled)
+    }
+
+    /// Set the maximum number of MAC commands processed per downlink (default:
+    /// [`mac::DEFAULT_MAX_DOWNLINK_MAC_COMMANDS`]). Further commands of the downlink are ignored,
+    /// guarding against downlinks stuffed with commands, and reported with
+    /// [`mac::MacEvent::MacCommandsDropped`].
+    pub fn set_max_downlink_mac_commands(&mut self, max: usize) {
+        self.shared.mac.set_max_downlink_mac_commands(max)
+    }
+
+    /// Set the battery level reported to the network in DevStatusAns: 0 for an external power
+    /// source, 1 (minimum) to 254 (maximum) for the battery level and 255 if it cannot be measured
+    /// (default).
+    pub fn set_battery_level(&mut self, battery_level: u8) {
+        self.shared.mac.set_battery_level(battery_level)
+    }
+
+    /// RSSI and SNR of the last frame accepted in a receive window, ie: a JoinAccept or a
+    /// downlink of the session passing the MIC check, if any.
+    pub fn last_rx_quality(&self) -> Option<RxQuality> {
+        self.shared.mac.last_rx_quality()
+    }
+
+    /// Replace the keys of an ABP session which were rotated out-of-band, resetting FCntUp and
+    /// FCntDown to 0. Returns an error if the device is not joined.
+    pub fn rekey_abp(&mut self, nwkskey: NwkSKey, appskey: AppSKey) -> Result<(), Error<R>> {
+        Ok(self.shared.mac.rekey_abp(nwkskey, appskey)?)
+    }
+
     pub fn get_radio(&mut self) -> &mut R {
         &mut self.shared.radio
     }
@@ -69,6 +153,60 @@ where
         self.shared.mac.configuration.data_rate = datarate
     }
 
+    /// Maximum application payload size for the next uplink, accounting for the current data
+    /// rate and any pending MAC commands which will be sent in FOpts.
+    pub fn available_app_payload(&self) -> usize {
+        self.shared.mac.available_app_payload()
+    }
+
+    /// Configure the NetID the device expects to be assigned a DevAddr from when joining. A
+    /// mismatch does not fail the join, but can be checked with [`Self::devaddr_matches_net_id`].
+    pub fn set_expected_net_id(&mut self, net_id: Option<u32>) {
+        self.shared.mac.set_expected_net_id(net_id)
+    }
+
+    /// Use a counter for the DevNonce of join requests, as required since LoRaWAN 1.0.4, instead
+    /// of random values. `next` is the DevNonce to use for the next join, eg: 0 for a new device,
+    /// or the value of [`Self::dev_nonce_counter`] persisted before a reset. Once all 2^16 values
+    /// have been used, joining fails with [`mac::Error::DevNonceExhausted`].
+    pub fn set_dev_nonce_counter(&mut self, next: Option<u32>) {
+        self.shared.mac.set_dev_nonce_counter(next)
+    }
+
+    /// Returns the DevNonce to use for the next join if a counter is used. It is incremented by
+    /// each join request, so it should be persisted after joining.
+    pub fn dev_nonce_counter(&self) -> Option<u32> {
+        self.shared.mac.dev_nonce_counter()
+    }
+
+    /// Configure after how many consecutive downlinks failing the MIC check a rejoin is
+    /// recommended with [`mac::MacEvent::RejoinRecommended`]. Disabled (`None`) by default.
+    pub fn set_mic_failure_threshold(&mut self, threshold: Option<u16>) {
+        self.shared.mac.set_mic_failure_threshold(threshold)
+    }
+
+    /// Take the next pending MAC event, if any. See [`mac::MacEvent`].
+    pub fn take_mac_event(&mut self) -> Option<mac::MacEvent> {
+        self.shared.mac.take_mac_event()
+    }
+
+    /// Returns whether the DevAddr of the current session belongs to the expected NetID, or `None`
+    /// if the device is not joined or no NetID is configured.
+    pub fn devaddr_matches_net_id(&self) -> Option<bool> {
+        self.shared.mac.devaddr_matches_net_id()
+    }
+
+    /// Whether the radio is listening in one of the receive windows following an uplink, eg: for
+    /// power management.
+    ///
+    /// The state follows the radio requests made by the device rather than a clock: a window is
+    /// reported open from the [`Event::TimeoutFired`] opening it until the one closing it, or
+    /// until a frame is received. This device does not support Class C, so there is no continuous
+    /// reception to report.
+    pub fn rx_window_state(&self) -> RxState {
+        self.state.rx_window_state()
+    }
+
     pub fn ready_to_send_data(&self) -> bool {
         matches!(&self.state, State::Idle(_)) && self.shared.mac.is_joined()
     }
@@ -99,6 +237,7 @@ where
 
     pub fn handle_event(&mut self, event: Event<'_, R>) -> Result<Response, Error<R>> {
         let (new_state, result) = self.state.handle_event::<R, C, RNG, N, D>(
+            &self.crypto,
             &mut self.shared.mac,
             &mut self.shared.radio,
             &mut self.shared.rng,
@@ -134,6 +273,18 @@ pub enum Response {
     RxComplete,
 }
 
+/// State of the receive windows which follow an uplink.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RxState {
+    /// No receive window is open. The device is either waiting for one to open, transmitting or
+    /// done with the last uplink.
+    Idle,
+    /// The radio is listening in the first receive window.
+    Rx1Open,
+    /// The radio is listening in the second receive window.
+    Rx2Open,
+}
+
 #[derive(Debug)]
 pub enum Error<R: PhyRxTx> {
     Radio(R::PhyError),
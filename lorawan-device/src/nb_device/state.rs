@@ -41,7 +41,7 @@ else(Ready)║ ╚═════════════╝   ║
 use super::super::*;
 use super::{
     mac::{Frame, Mac, Window},
-    radio, Event, RadioBuffer, Response, Timings,
+    radio, Event, RadioBuffer, Response, RxState, Timings,
 };
 
 #[derive(Copy, Clone)]
@@ -66,6 +66,16 @@ macro_rules! into_state {
 
 into_state!(Idle, SendingData, WaitingForRxWindow, WaitingForRx);
 
+impl State {
+    pub(crate) fn rx_window_state(&self) -> RxState {
+        match self {
+            State::WaitingForRx(WaitingForRx { window: Rx::_1(_), .. }) => RxState::Rx1Open,
+            State::WaitingForRx(WaitingForRx { window: Rx::_2(_), .. }) => RxState::Rx2Open,
+            _ => RxState::Idle,
+        }
+    }
+}
+
 impl Default for State {
     fn default() -> Self {
         State::Idle(Idle)
@@ -101,14 +111,16 @@ impl<R: radio::PhyRxTx> From<Error> for super::Error<R> {
 }
 
 impl State {
+    #[allow(clippy::too_many_arguments)]
     pub(crate) fn handle_event<
         R: radio::PhyRxTx + Timings,
-        C: CryptoFactory + Default,
+        C: CryptoFactory,
         RNG: RngCore,
         const N: usize,
         const D: usize,
     >(
         self,
+        crypto: &C,
         mac: &mut Mac,
         radio: &mut R,
         rng: &mut RNG,
@@ -117,10 +129,12 @@ impl State {
         event: Event<'_, R>,
     ) -> (Self, Result<Response, super::Error<R>>) {
         match self {
-            State::Idle(s) => s.handle_event::<R, C, RNG, N>(mac, radio, rng, buf, event),
+            State::Idle(s) => s.handle_event::<R, C, RNG, N>(crypto, mac, radio, rng, buf, event),
             State::SendingData(s) => s.handle_event::<R, N>(mac, radio, event),
             State::WaitingForRxWindow(s) => s.handle_event::<R, N>(mac, radio, event),
-            State::WaitingForRx(s) => s.handle_event::<R, C, N, D>(mac, radio, buf, event, dl),
+            State::WaitingForRx(s) => {
+                s.handle_event::<R, C, N, D>(crypto, mac, radio, buf, event, dl)
+            }
         }
     }
 }
@@ -131,11 +145,12 @@ pub struct Idle;
 impl Idle {
     pub(crate) fn handle_event<
         R: radio::PhyRxTx + Timings,
-        C: CryptoFactory + Default,
+        C: CryptoFactory,
         RNG: RngCore,
         const N: usize,
     >(
         self,
+        crypto: &C,
         mac: &mut Mac,
         radio: &mut R,
         rng: &mut RNG,
@@ -149,16 +164,18 @@ impl Idle {
 
         let response = match event {
             // tolerate unexpected timeout
-            Event::Join(creds) => {
-                let (tx_config, dev_nonce) = mac.join_otaa::<C, RNG, N>(rng, creds, buf);
-                IntermediateResponse::RadioTx((Frame::Join, tx_config, dev_nonce as u32))
-            }
+            Event::Join(creds) => match mac.join_otaa::<C, RNG, N>(crypto, rng, creds, buf) {
+                Err(e) => IntermediateResponse::EarlyReturn(Err(e.into())),
+                Ok((tx_config, dev_nonce)) => {
+                    IntermediateResponse::RadioTx((Frame::Join, tx_config, dev_nonce as u32))
+                }
+            },
             Event::TimeoutFired => IntermediateResponse::EarlyReturn(Ok(Response::NoUpdate)),
             Event::RadioEvent(_radio_event) => {
                 IntermediateResponse::EarlyReturn(Err(Error::RadioEventWhileIdle.into()))
             }
             Event::SendDataRequest(send_data) => {
-                let tx_config = mac.send::<C, RNG, N>(rng, buf, &send_data);
+                let tx_config = mac.send::<C, RNG, N>(crypto, rng, buf, &send_data);
                 match tx_config {
                     Err(e) => IntermediateResponse::EarlyReturn(Err(e.into())),
                     Ok((tx_config, fcnt_up)) => {
@@ -312,11 +329,12 @@ pub struct WaitingForRx {
 impl WaitingForRx {
     pub(crate) fn handle_event<
         R: radio::PhyRxTx + Timings,
-        C: CryptoFactory + Default,
+        C: CryptoFactory,
         const N: usize,
         const D: usize,
     >(
         self,
+        crypto: &C,
         mac: &mut Mac,
         radio: &mut R,
         buf: &mut RadioBuffer<N>,
@@ -329,7 +347,7 @@ impl WaitingForRx {
                 // send the transmit request to the radio
                 match radio.handle_event(radio_event) {
                     Ok(response) => match response {
-                        radio::Response::RxDone(_quality) => {
+                        radio::Response::RxDone(quality) => {
                             // copy from radio buffer to mac buffer
                             buf.clear();
                             if let Err(()) =
@@ -340,7 +358,7 @@ impl WaitingForRx {
                                     Err(Error::BufferTooSmall.into()),
                                 );
                             }
-                            match mac.handle_rx::<C, N, D>(buf, dl) {
+                            match mac.handle_rx::<C, N, D>(crypto, buf, quality, dl) {
                                 // NoUpdate can occur when a stray radio packet is received. Maintain state
                                 mac::Response::NoUpdate => {
                                     (State::WaitingForRx(self), Ok(Response::NoUpdate))
@@ -4,6 +4,7 @@ use crate::test_util::*;
 use util::*;
 
 use crate::nb_device::Event;
+use lorawan::parser::{DataHeader, DataPayload, FRMPayload, PhyPayload};
 #[test]
 fn test_join_rx1() {
     let mut device = test_device();
@@ -54,6 +55,24 @@ fn test_unconfirmed_uplink_no_downlink() {
     let response = device.handle_event(Event::TimeoutFired).unwrap(); // end Rx2
     assert!(matches!(response, Response::RxComplete));
 }
+#[test]
+fn test_rx_window_state() {
+    let mut device = test_device();
+    device.join(get_abp_credentials()).unwrap();
+    assert_eq!(device.rx_window_state(), RxState::Idle);
+    device.send(&[0; 1], 1, false).unwrap();
+    assert_eq!(device.rx_window_state(), RxState::Idle);
+    device.handle_event(Event::TimeoutFired).unwrap(); // begin Rx1
+    assert_eq!(device.rx_window_state(), RxState::Rx1Open);
+    device.handle_event(Event::TimeoutFired).unwrap(); // end Rx1
+    assert_eq!(device.rx_window_state(), RxState::Idle);
+    device.handle_event(Event::TimeoutFired).unwrap(); // begin Rx2
+    assert_eq!(device.rx_window_state(), RxState::Rx2Open);
+    let response = device.handle_event(Event::TimeoutFired).unwrap(); // end Rx2
+    assert!(matches!(response, Response::RxComplete));
+    assert_eq!(device.rx_window_state(), RxState::Idle);
+}
+
 #[test]
 fn test_confirmed_uplink_no_ack() {
     let mut device = test_device();
@@ -105,6 +124,28 @@ fn test_confirmed_uplink_with_ack_rx2() {
     assert!(matches!(response, Response::DownlinkReceived(0)));
 }
 
+#[test]
+fn test_confirmed_downlink_acked_by_next_uplink() {
+    let mut device = test_device();
+    let response = device.join(get_abp_credentials());
+    assert!(matches!(response, Ok(Response::JoinSuccess)));
+    let response = device.send(&[0; 1], 1, true).unwrap();
+    assert!(matches!(response, Response::TimeoutRequest(1000)));
+    let response = device.handle_event(Event::TimeoutFired).unwrap(); // begin Rx1
+    assert!(matches!(response, Response::TimeoutRequest(1100)));
+    // the downlink answering a confirmed uplink is itself confirmed
+    device.get_radio().set_rxtx_handler(handle_data_uplink_with_link_adr_req::<0, 0>);
+    let response = device.handle_event(Event::RadioEvent(radio::Event::Phy(()))).unwrap();
+    assert!(matches!(response, Response::DownlinkReceived(0)));
+    let response = device.send(&[0; 1], 1, false).unwrap();
+    assert!(matches!(response, Response::TimeoutRequest(1000)));
+    let response = device.handle_event(Event::TimeoutFired).unwrap(); // begin Rx1
+    assert!(matches!(response, Response::TimeoutRequest(1100)));
+    device.get_radio().set_rxtx_handler(handle_data_uplink_with_ack);
+    let response = device.handle_event(Event::RadioEvent(radio::Event::Phy(()))).unwrap();
+    assert!(matches!(response, Response::NoUpdate));
+}
+
 #[test]
 fn test_link_adr_ans() {
     let mut device = test_device();
@@ -128,3 +169,560 @@ fn test_link_adr_ans() {
     let response = device.handle_event(Event::RadioEvent(radio::Event::Phy(()))).unwrap();
     assert!(matches!(response, Response::DownlinkReceived(1)));
 }
+
+#[test]
+fn test_available_app_payload_reduced_by_pending_macs() {
+    let mut device = test_device();
+    let response = device.join(get_abp_credentials());
+    assert!(matches!(response, Ok(Response::JoinSuccess)));
+    device.set_datarate(region::DR::_1);
+    let available = device.available_app_payload();
+    // US915 DR1 allows for a 61 byte MAC payload minus FHDR (7) and FPort (1)
+    assert_eq!(available, 53);
+    let response = device.send(&[0; 1], 1, true).unwrap();
+    assert!(matches!(response, Response::TimeoutRequest(1000)));
+    let response = device.handle_event(Event::TimeoutFired).unwrap(); // begin Rx1
+    assert!(matches!(response, Response::TimeoutRequest(1100)));
+    device.get_radio().set_rxtx_handler(handle_data_uplink_with_link_adr_req::<0, 0>);
+    let response = device.handle_event(Event::RadioEvent(radio::Event::Phy(()))).unwrap();
+    assert!(matches!(response, Response::DownlinkReceived(0)));
+    // the two pending LinkADRAns (CID + status) will consume four bytes of FOpts
+    assert_eq!(device.available_app_payload(), available - 4);
+}
+
+#[test]
+fn test_join_net_id_match() {
+    let mut device = test_device();
+    // DevAddr 0 has a NwkID of 0
+    device.set_expected_net_id(Some(0x000080));
+    device.get_radio().set_rxtx_handler(handle_join_request::<1>);
+    device.join(get_otaa_credentials()).unwrap();
+    device.handle_event(Event::TimeoutFired).unwrap();
+    let response = device.handle_event(Event::RadioEvent(radio::Event::Phy(()))).unwrap();
+    assert!(matches!(response, Response::JoinSuccess));
+    assert_eq!(device.devaddr_matches_net_id(), Some(true));
+}
+
+#[test]
+fn test_join_net_id_mismatch() {
+    let mut device = test_device();
+    device.set_expected_net_id(Some(0x000013));
+    device.get_radio().set_rxtx_handler(handle_join_request::<1>);
+    device.join(get_otaa_credentials()).unwrap();
+    device.handle_event(Event::TimeoutFired).unwrap();
+    let response = device.handle_event(Event::RadioEvent(radio::Event::Phy(()))).unwrap();
+    // the join still succeeds, as the device may be roaming
+    assert!(matches!(response, Response::JoinSuccess));
+    assert_eq!(device.devaddr_matches_net_id(), Some(false));
+}
+
+#[test]
+fn test_duplicate_downlink_ignored() {
+    let mut device = test_device();
+    let response = device.join(get_abp_credentials());
+    assert!(matches!(response, Ok(Response::JoinSuccess)));
+    let available = device.available_app_payload();
+    let response = device.send(&[0; 1], 1, true).unwrap();
+    assert!(matches!(response, Response::TimeoutRequest(1000)));
+    let response = device.handle_event(Event::TimeoutFired).unwrap(); // begin Rx1
+    assert!(matches!(response, Response::TimeoutRequest(1100)));
+    device.get_radio().set_rxtx_handler(handle_data_uplink_with_link_adr_req::<0, 0>);
+    let response = device.handle_event(Event::RadioEvent(radio::Event::Phy(()))).unwrap();
+    assert!(matches!(response, Response::DownlinkReceived(0)));
+    assert!(device.take_downlink().is_some());
+    // the next uplink carries the LinkADRAns, the network repeats the downlink with FCntDown 0
+    let response = device.send(&[0; 1], 1, true).unwrap();
+    assert!(matches!(response, Response::TimeoutRequest(1000)));
+    assert_eq!(device.available_app_payload(), available);
+    let response = device.handle_event(Event::TimeoutFired).unwrap(); // begin Rx1
+    assert!(matches!(response, Response::TimeoutRequest(1100)));
+    device.get_radio().set_rxtx_handler(handle_data_uplink_with_link_adr_req::<1, 0>);
+    let response = device.handle_event(Event::RadioEvent(radio::Event::Phy(()))).unwrap();
+    // the duplicate is ignored: no MAC commands applied and no data delivered
+    assert!(matches!(response, Response::NoUpdate));
+    assert!(device.take_downlink().is_none());
+    assert_eq!(device.available_app_payload(), available);
+    let response = device.handle_event(Event::TimeoutFired).unwrap(); // end Rx1
+    assert!(matches!(response, Response::TimeoutRequest(2000)));
+}
+
+fn receive_bad_mic_in_rx1(
+    device: &mut Device<TestRadio, lorawan::default_crypto::DefaultFactory, rand_core::OsRng, 255>,
+) {
+    let response = device.send(&[0; 1], 1, false).unwrap();
+    assert!(matches!(response, Response::TimeoutRequest(1000)));
+    let response = device.handle_event(Event::TimeoutFired).unwrap(); // begin Rx1
+    assert!(matches!(response, Response::TimeoutRequest(1100)));
+    device.get_radio().set_rxtx_handler(handle_data_uplink_with_bad_mic::<0>);
+    let response = device.handle_event(Event::RadioEvent(radio::Event::Phy(()))).unwrap();
+    assert!(matches!(response, Response::NoUpdate));
+    assert!(device.take_downlink().is_none());
+    let response = device.handle_event(Event::TimeoutFired).unwrap(); // end Rx1
+    assert!(matches!(response, Response::TimeoutRequest(2000)));
+    let response = device.handle_event(Event::TimeoutFired).unwrap(); // begin Rx2
+    assert!(matches!(response, Response::TimeoutRequest(2100)));
+    let response = device.handle_event(Event::TimeoutFired).unwrap(); // end Rx2
+    assert!(matches!(response, Response::RxComplete));
+}
+
+#[test]
+fn test_rejoin_recommended_after_mic_failures() {
+    let mut device = test_device();
+    let response = device.join(get_abp_credentials());
+    assert!(matches!(response, Ok(Response::JoinSuccess)));
+    device.set_mic_failure_threshold(Some(3));
+    for _ in 0..2 {
+        receive_bad_mic_in_rx1(&mut device);
+        assert_eq!(device.take_mac_event(), None);
+    }
+    receive_bad_mic_in_rx1(&mut device);
+    assert_eq!(device.take_mac_event(), Some(mac::MacEvent::RejoinRecommended));
+    assert_eq!(device.take_mac_event(), None);
+    // the event is raised once when reaching the threshold
+    receive_bad_mic_in_rx1(&mut device);
+    assert_eq!(device.take_mac_event(), None);
+    assert_eq!(device.get_session().unwrap().mic_failures, 4);
+
+    // a downlink with a valid MIC resets the count
+    let response = device.send(&[0; 1], 1, false).unwrap();
+    assert!(matches!(response, Response::TimeoutRequest(1000)));
+    let response = device.handle_event(Event::TimeoutFired).unwrap(); // begin Rx1
+    assert!(matches!(response, Response::TimeoutRequest(1100)));
+    device.get_radio().set_rxtx_handler(handle_data_uplink_with_link_adr_req::<4, 0>);
+    let response = device.handle_event(Event::RadioEvent(radio::Event::Phy(()))).unwrap();
+    assert!(matches!(response, Response::DownlinkReceived(0)));
+    assert_eq!(device.get_session().unwrap().mic_failures, 0);
+}
+
+#[test]
+fn test_fcnt_down_rollover_of_16_lsb() {
+    let mut device = test_device();
+    let mut session = crate::mac::Session::new(get_key().into(), get_key().into(), get_dev_addr());
+    session.fcnt_down = 0xfffe;
+    session.downlink_received = true;
+    device.set_session(session);
+    let response = device.send(&[0; 1], 1, false).unwrap();
+    assert!(matches!(response, Response::TimeoutRequest(1000)));
+    let response = device.handle_event(Event::TimeoutFired).unwrap(); // begin Rx1
+    assert!(matches!(response, Response::TimeoutRequest(1100)));
+    // the FHDR only carries 0x0001 while the MIC is computed over 0x1_0001
+    device.get_radio().set_rxtx_handler(handle_data_uplink_with_link_adr_req::<0, 0x1_0001>);
+    let response = device.handle_event(Event::RadioEvent(radio::Event::Phy(()))).unwrap();
+    assert!(matches!(response, Response::DownlinkReceived(0x1_0001)));
+    assert_eq!(device.get_session().unwrap().fcnt_down, 0x1_0001);
+}
+
+#[test]
+fn test_older_fcnt_down_rejected() {
+    let mut device = test_device();
+    let mut session = crate::mac::Session::new(get_key().into(), get_key().into(), get_dev_addr());
+    session.fcnt_down = 0x1_0005;
+    session.downlink_received = true;
+    device.set_session(session);
+    let response = device.send(&[0; 1], 1, false).unwrap();
+    assert!(matches!(response, Response::TimeoutRequest(1000)));
+    let response = device.handle_event(Event::TimeoutFired).unwrap(); // begin Rx1
+    assert!(matches!(response, Response::TimeoutRequest(1100)));
+    // a replayed older frame is neither taken as a rollover nor counted as a MIC failure
+    device.get_radio().set_rxtx_handler(handle_data_uplink_with_link_adr_req::<0, 0x1_0004>);
+    let response = device.handle_event(Event::RadioEvent(radio::Event::Phy(()))).unwrap();
+    assert!(matches!(response, Response::NoUpdate));
+    assert!(device.take_downlink().is_none());
+    let session = device.get_session().unwrap();
+    assert_eq!(session.fcnt_down, 0x1_0005);
+    assert_eq!(session.mic_failures, 0);
+}
+
+fn handle_uplink_after_rekey(
+    uplink: Option<crate::test_util::Uplink>,
+    _config: crate::radio::RfConfig,
+    _rx_buffer: &mut [u8],
+) -> usize {
+    let mut uplink = uplink.unwrap();
+    if let PhyPayload::Data(DataPayload::Encrypted(data)) = uplink.get_payload() {
+        assert_eq!(data.fhdr().fcnt(), 0);
+        assert!(!data.validate_mic(&get_key().into(), 0));
+        assert!(data.validate_mic(&[1; 16].into(), 0));
+        let uplink = data.decrypt(Some(&[1; 16].into()), Some(&[2; 16].into()), 0).unwrap();
+        assert!(matches!(uplink.frm_payload(), FRMPayload::Data(&[0xab])));
+    } else {
+        panic!("expected a data uplink");
+    }
+    0
+}
+
+#[test]
+fn test_rekey_abp() {
+    let mut device = test_device();
+    assert!(device.rekey_abp(NwkSKey::from([1; 16]), AppSKey::from([2; 16])).is_err());
+    let response = device.join(get_abp_credentials());
+    assert!(matches!(response, Ok(Response::JoinSuccess)));
+    let response = device.send(&[0; 1], 1, false).unwrap();
+    assert!(matches!(response, Response::TimeoutRequest(1000)));
+    let response = device.handle_event(Event::TimeoutFired).unwrap(); // begin Rx1
+    assert!(matches!(response, Response::TimeoutRequest(1100)));
+    // consume the uplink without answering
+    device.get_radio().set_rxtx_handler(|_, _, _| 0);
+    let response = device.handle_event(Event::RadioEvent(radio::Event::Phy(()))).unwrap();
+    assert!(matches!(response, Response::NoUpdate));
+    let response = device.handle_event(Event::TimeoutFired).unwrap(); // end Rx1
+    assert!(matches!(response, Response::TimeoutRequest(2000)));
+    let response = device.handle_event(Event::TimeoutFired).unwrap(); // being Rx2
+    assert!(matches!(response, Response::TimeoutRequest(2100)));
+    let response = device.handle_event(Event::TimeoutFired).unwrap(); // end Rx2
+    assert!(matches!(response, Response::RxComplete));
+    assert_eq!(device.get_fcnt_up(), Some(1));
+
+    device.rekey_abp(NwkSKey::from([1; 16]), AppSKey::from([2; 16])).unwrap();
+    assert_eq!(device.get_fcnt_up(), Some(0));
+    let response = device.send(&[0xab], 1, false).unwrap();
+    assert!(matches!(response, Response::TimeoutRequest(1000)));
+    let response = device.handle_event(Event::TimeoutFired).unwrap(); // begin Rx1
+    assert!(matches!(response, Response::TimeoutRequest(1100)));
+    device.get_radio().set_rxtx_handler(handle_uplink_after_rekey);
+    // the handler checks the uplink against the new keys and counter
+    let response = device.handle_event(Event::RadioEvent(radio::Event::Phy(()))).unwrap();
+    assert!(matches!(response, Response::NoUpdate));
+}
+
+#[test]
+fn test_dev_status_ans() {
+    let mut device = test_device();
+    let response = device.join(get_abp_credentials());
+    assert!(matches!(response, Ok(Response::JoinSuccess)));
+    device.set_battery_level(100);
+    let available = device.available_app_payload();
+    let response = device.send(&[0; 1], 1, false).unwrap();
+    assert!(matches!(response, Response::TimeoutRequest(1000)));
+    let response = device.handle_event(Event::TimeoutFired).unwrap(); // begin Rx1
+    assert!(matches!(response, Response::TimeoutRequest(1100)));
+    device.get_radio().set_rxtx_handler(handle_data_uplink_with_dev_status_req);
+    let response = device.handle_event(Event::RadioEvent(radio::Event::Phy(()))).unwrap();
+    assert!(matches!(response, Response::DownlinkReceived(0)));
+    // DevStatusAns is queued for the next uplink: CID + battery + margin
+    assert_eq!(device.available_app_payload(), available - 3);
+    let response = device.send(&[0; 1], 1, false).unwrap();
+    assert!(matches!(response, Response::TimeoutRequest(1000)));
+    assert_eq!(device.available_app_payload(), available);
+    let response = device.handle_event(Event::TimeoutFired).unwrap(); // begin Rx1
+    assert!(matches!(response, Response::TimeoutRequest(1100)));
+    device.get_radio().set_rxtx_handler(handle_data_uplink_with_dev_status_ans::<0>);
+    let response = device.handle_event(Event::RadioEvent(radio::Event::Phy(()))).unwrap();
+    assert!(matches!(response, Response::NoUpdate));
+}
+
+#[test]
+fn test_mac_answers_exceeding_fopts_deferred() {
+    let mut device = test_device();
+    let response = device.join(get_abp_credentials());
+    assert!(matches!(response, Ok(Response::JoinSuccess)));
+    let response = device.send(&[0; 1], 1, false).unwrap();
+    assert!(matches!(response, Response::TimeoutRequest(1000)));
+    let response = device.handle_event(Event::TimeoutFired).unwrap(); // begin Rx1
+    assert!(matches!(response, Response::TimeoutRequest(1100)));
+    device.get_radio().set_rxtx_handler(handle_data_uplink_with_fopts_overflowing_mac_commands);
+    let response = device.handle_event(Event::RadioEvent(radio::Event::Phy(()))).unwrap();
+    assert!(matches!(response, Response::DownlinkReceived(0)));
+    let response = device.send(&[0; 1], 1, false).unwrap();
+    assert!(matches!(response, Response::TimeoutRequest(1000)));
+    let response = device.handle_event(Event::TimeoutFired).unwrap(); // begin Rx1
+    assert!(matches!(response, Response::TimeoutRequest(1100)));
+    // the 16 bytes of answers exceed FOpts, so the last NewChannelAns is deferred
+    device.get_radio().set_rxtx_handler(handle_data_uplink_with_fopts_limited_answers);
+    let response = device.handle_event(Event::RadioEvent(radio::Event::Phy(()))).unwrap();
+    assert!(matches!(response, Response::NoUpdate));
+    let response = device.handle_event(Event::TimeoutFired).unwrap(); // end Rx1
+    assert!(matches!(response, Response::TimeoutRequest(2000)));
+    let response = device.handle_event(Event::TimeoutFired).unwrap(); // begin Rx2
+    assert!(matches!(response, Response::TimeoutRequest(2100)));
+    let response = device.handle_event(Event::TimeoutFired).unwrap(); // end Rx2
+    assert!(matches!(response, Response::RxComplete));
+    let response = device.send(&[0; 1], 1, false).unwrap();
+    assert!(matches!(response, Response::TimeoutRequest(1000)));
+    let response = device.handle_event(Event::TimeoutFired).unwrap(); // begin Rx1
+    assert!(matches!(response, Response::TimeoutRequest(1100)));
+    device.get_radio().set_rxtx_handler(handle_data_uplink_with_deferred_new_channel_ans);
+    let response = device.handle_event(Event::RadioEvent(radio::Event::Phy(()))).unwrap();
+    assert!(matches!(response, Response::NoUpdate));
+}
+
+#[test]
+fn test_dev_status_ans_margin_from_recorded_snr() {
+    let mut device = test_device();
+    let response = device.join(get_abp_credentials());
+    assert!(matches!(response, Ok(Response::JoinSuccess)));
+    device.set_battery_level(100);
+    assert_eq!(device.last_rx_quality(), None);
+    let response = device.send(&[0; 1], 1, false).unwrap();
+    assert!(matches!(response, Response::TimeoutRequest(1000)));
+    let response = device.handle_event(Event::TimeoutFired).unwrap(); // begin Rx1
+    assert!(matches!(response, Response::TimeoutRequest(1100)));
+    device.get_radio().set_rxtx_handler(handle_data_uplink_with_dev_status_req);
+    device.get_radio().set_rx_quality(crate::radio::RxQuality::new(-110, -7));
+    let response = device.handle_event(Event::RadioEvent(radio::Event::Phy(()))).unwrap();
+    assert!(matches!(response, Response::DownlinkReceived(0)));
+    assert_eq!(device.last_rx_quality(), Some(crate::radio::RxQuality::new(-110, -7)));
+    // the DevStatusAns margin is the SNR of the downlink which carried the DevStatusReq
+    let response = device.send(&[0; 1], 1, false).unwrap();
+    assert!(matches!(response, Response::TimeoutRequest(1000)));
+    let response = device.handle_event(Event::TimeoutFired).unwrap(); // begin Rx1
+    assert!(matches!(response, Response::TimeoutRequest(1100)));
+    device.get_radio().set_rxtx_handler(handle_data_uplink_with_dev_status_ans::<-7>);
+    let response = device.handle_event(Event::RadioEvent(radio::Event::Phy(()))).unwrap();
+    assert!(matches!(response, Response::NoUpdate));
+}
+
+#[test]
+fn test_dev_status_ans_margin_ignores_foreign_frame() {
+    use lorawan::maccommands::{
+        parse_uplink_mac_commands, DevStatusReqPayload, DownlinkMacCommand, UplinkMacCommand,
+        UplinkMacCommandCreator,
+    };
+
+    let mut device = test_device();
+    device.join(get_abp_credentials()).unwrap();
+    device.set_battery_level(100);
+    device.send(&[0; 1], 1, false).unwrap();
+    device.handle_event(Event::TimeoutFired).unwrap(); // begin Rx1
+    device.get_radio().set_rxtx_handler(handle_data_uplink_with_dev_status_req);
+    device.get_radio().set_rx_quality(crate::radio::RxQuality::new(-110, -7));
+    let response = device.handle_event(Event::RadioEvent(radio::Event::Phy(()))).unwrap();
+    assert!(matches!(response, Response::DownlinkReceived(0)));
+
+    // a frame failing the MIC check, eg: for another device, is not accounted for
+    device.send(&[0; 1], 1, false).unwrap();
+    device.handle_event(Event::TimeoutFired).unwrap(); // begin Rx1
+    device.get_radio().set_rxtx_handler(handle_data_uplink_with_bad_mic::<1>);
+    device.get_radio().set_rx_quality(crate::radio::RxQuality::new(-40, 9));
+    let response = device.handle_event(Event::RadioEvent(radio::Event::Phy(()))).unwrap();
+    assert!(matches!(response, Response::NoUpdate));
+    assert_eq!(device.last_rx_quality(), Some(crate::radio::RxQuality::new(-110, -7)));
+
+    let cmd = DownlinkMacCommand::DevStatusReq(DevStatusReqPayload());
+    let Some(UplinkMacCommandCreator::DevStatusAns(ans)) = device.apply_mac_command(cmd) else {
+        panic!("Expected a DevStatusAns");
+    };
+    let ans = parse_uplink_mac_commands(ans.build()).next();
+    assert!(matches!(ans, Some(UplinkMacCommand::DevStatusAns(ans)) if ans.margin() == -7));
+}
+
+#[test]
+fn test_link_check() {
+    let mut device = test_device();
+    assert!(device.request_link_check().is_err());
+    let response = device.join(get_abp_credentials());
+    assert!(matches!(response, Ok(Response::JoinSuccess)));
+    let available = device.available_app_payload();
+    device.request_link_check().unwrap();
+    assert_eq!(device.available_app_payload(), available - 1);
+    let response = device.send(&[0; 1], 1, false).unwrap();
+    assert!(matches!(response, Response::TimeoutRequest(1000)));
+    let response = device.handle_event(Event::TimeoutFired).unwrap(); // begin Rx1
+    assert!(matches!(response, Response::TimeoutRequest(1100)));
+    device.get_radio().set_rxtx_handler(handle_data_uplink_with_link_check_req);
+    let response = device.handle_event(Event::RadioEvent(radio::Event::Phy(()))).unwrap();
+    assert!(matches!(response, Response::DownlinkReceived(0)));
+    assert_eq!(
+        device.take_link_check_ans(),
+        Some(mac::LinkCheckAns { margin: 20, gateway_count: 3 })
+    );
+    assert_eq!(device.take_link_check_ans(), None);
+}
+
+#[test]
+fn test_device_time() {
+    let mut device = test_device();
+    assert!(device.request_device_time().is_err());
+    let response = device.join(get_abp_credentials());
+    assert!(matches!(response, Ok(Response::JoinSuccess)));
+    let available = device.available_app_payload();
+    device.request_device_time().unwrap();
+    assert_eq!(device.available_app_payload(), available - 1);
+    let response = device.send(&[0; 1], 1, false).unwrap();
+    assert!(matches!(response, Response::TimeoutRequest(1000)));
+    let response = device.handle_event(Event::TimeoutFired).unwrap(); // begin Rx1
+    assert!(matches!(response, Response::TimeoutRequest(1100)));
+    device.get_radio().set_rxtx_handler(handle_data_uplink_with_device_time_req);
+    let response = device.handle_event(Event::RadioEvent(radio::Event::Phy(()))).unwrap();
+    assert!(matches!(response, Response::DownlinkReceived(0)));
+    let payload = [0x12, 0x34, 0x56, 0x78, 0x80];
+    let expected = lorawan::maccommands::DeviceTimeAnsPayload::new(&payload).unwrap();
+    assert_eq!(
+        device.take_device_time_ans(),
+        Some(mac::DeviceTimeAns { seconds: expected.seconds(), nano_seconds: 500_000_000 })
+    );
+    assert_eq!(device.take_device_time_ans(), None);
+    // the request is only sent once
+    assert_eq!(device.available_app_payload(), available);
+}
+
+#[test]
+fn test_max_downlink_mac_commands() {
+    let mut device = test_device();
+    device.set_max_downlink_mac_commands(2);
+    let response = device.join(get_abp_credentials());
+    assert!(matches!(response, Ok(Response::JoinSuccess)));
+    let available = device.available_app_payload();
+    let response = device.send(&[0; 1], 1, false).unwrap();
+    assert!(matches!(response, Response::TimeoutRequest(1000)));
+    let response = device.handle_event(Event::TimeoutFired).unwrap(); // begin Rx1
+    assert!(matches!(response, Response::TimeoutRequest(1100)));
+    device.get_radio().set_rxtx_handler(handle_data_uplink_with_frm_payload_mac_commands);
+    let response = device.handle_event(Event::RadioEvent(radio::Event::Phy(()))).unwrap();
+    assert!(matches!(response, Response::DownlinkReceived(0)));
+    // DevStatusReq and LinkCheckAns are processed, DeviceTimeAns is over the limit
+    assert_eq!(device.available_app_payload(), available - 3);
+    assert_eq!(
+        device.take_link_check_ans(),
+        Some(mac::LinkCheckAns { margin: 20, gateway_count: 3 })
+    );
+    assert_eq!(device.take_device_time_ans(), None);
+    assert_eq!(device.take_mac_event(), Some(mac::MacEvent::MacCommandsDropped(1)));
+    assert_eq!(device.take_mac_event(), None);
+}
+
+#[test]
+fn test_join_accept_schedule() {
+    let region = region::Configuration::new(region::Region::US915);
+    let mut buf = [0u8; 23];
+    let mut phy = lorawan::creator::JoinRequestCreator::new(&mut buf[..]).unwrap();
+    phy.set_app_eui(AppEui::from([0; 8])).set_dev_eui(DevEui::from([0; 8])).set_dev_nonce(&[1, 2]);
+    let join_request =
+        phy.build(&AppKey::from(get_key()), &lorawan::default_crypto::DefaultFactory);
+    let schedule = JoinAcceptSchedule::new(join_request, &region, region::DR::_0);
+    assert_eq!(schedule.delays, (5000, 6000));
+    assert_eq!(schedule.rx1_frame, schedule.rx2_frame);
+    // RX2 uses the default RX2 frequency and data rate of the region, whatever the uplink DR
+    assert_eq!(schedule.rx2_config, region.get_rx2_config());
+    assert_eq!(schedule.rx2_config.bb.sf, lora_modulation::SpreadingFactor::_12);
+    assert_eq!(schedule.rx2_config.bb.bw, lora_modulation::Bandwidth::_500KHz);
+    // RX1 follows the uplink DR (DR0 -> DR10)
+    assert_eq!(schedule.rx1_config.bb.sf, lora_modulation::SpreadingFactor::_10);
+}
+
+#[test]
+fn test_uplink_dwell_time_exceeded() {
+    let mut device: Device<
+        TestRadio,
+        lorawan::default_crypto::DefaultFactory,
+        rand_core::OsRng,
+        255,
+    > = Device::new(
+        region::Configuration::new(region::Region::AS923_1),
+        TestRadio::default(),
+        rand::rngs::OsRng,
+    );
+    // an unjoined device cannot send, whatever the payload size
+    device.set_uplink_dwell_time(true);
+    let response = device.send(&[0; 12], 1, false);
+    assert!(matches!(response, Err(Error::Mac(mac::Error::NotJoined))));
+    device.set_uplink_dwell_time(false);
+    let response = device.join(get_abp_credentials());
+    assert!(matches!(response, Ok(Response::JoinSuccess)));
+    device.set_datarate(region::DR::_2);
+    assert_eq!(device.available_app_payload(), 115);
+    device.set_uplink_dwell_time(true);
+    // AS923 DR2 allows a 19 byte MAC payload with dwell time limitations
+    assert_eq!(device.available_app_payload(), 11);
+    let response = device.send(&[0; 12], 1, false);
+    assert!(matches!(response, Err(Error::Mac(mac::Error::DwellTimeExceeded { max: 11 }))));
+    let response = device.send(&[0; 11], 1, false).unwrap();
+    assert!(matches!(response, Response::TimeoutRequest(_)));
+}
+
+#[test]
+fn test_apply_new_channel_req() {
+    use lorawan::maccommands::{DownlinkMacCommand, NewChannelReqPayload, UplinkMacCommandCreator};
+
+    let mut device: Device<
+        TestRadio,
+        lorawan::default_crypto::DefaultFactory,
+        rand_core::OsRng,
+        255,
+    > = Device::new(
+        region::Configuration::new(region::Region::EU868),
+        TestRadio::default(),
+        rand::rngs::OsRng,
+    );
+    let mut apply = |payload: &[u8; 5]| {
+        let cmd = DownlinkMacCommand::NewChannelReq(NewChannelReqPayload::new(payload).unwrap());
+        match device.apply_mac_command(cmd) {
+            Some(UplinkMacCommandCreator::NewChannelAns(ans)) => ans.build()[1],
+            ans => panic!("Unexpected answer: {ans:?}"),
+        }
+    };
+    // channel 3 at 867.1 MHz, DR0 to DR5
+    assert_eq!(apply(&[3, 0x18, 0x4e, 0x84, 0x50]), 0x03);
+    // DR15 is not defined in EU868
+    assert_eq!(apply(&[4, 0x18, 0x4e, 0x84, 0xf0]), 0x01);
+    // join channels cannot be modified
+    assert_eq!(apply(&[0, 0x18, 0x4e, 0x84, 0x50]), 0x00);
+    // disabling a channel ignores the data rate range
+    assert_eq!(apply(&[3, 0, 0, 0, 0xf0]), 0x03);
+}
+
+#[test]
+fn test_apply_new_channel_req_fixed_channel_plan() {
+    use lorawan::maccommands::{DownlinkMacCommand, NewChannelReqPayload, UplinkMacCommandCreator};
+
+    let mut device = test_device();
+    let payload = [8, 0x18, 0x4e, 0x84, 0x50];
+    let cmd = DownlinkMacCommand::NewChannelReq(NewChannelReqPayload::new(&payload).unwrap());
+    let ans = device.apply_mac_command(cmd);
+    assert!(
+        matches!(ans, Some(UplinkMacCommandCreator::NewChannelAns(ans)) if ans.build() == [0x07, 0x00])
+    );
+}
+
+use lorawan::default_crypto::DefaultFactory;
+use lorawan::keys::{CryptoFactory, AES128};
+
+/// Software crypto factory counting the primitives it creates.
+#[derive(Default)]
+struct CountingFactory {
+    created: core::cell::Cell<usize>,
+}
+
+impl CryptoFactory for CountingFactory {
+    type E = <DefaultFactory as CryptoFactory>::E;
+    type D = <DefaultFactory as CryptoFactory>::D;
+    type M = <DefaultFactory as CryptoFactory>::M;
+
+    fn new_enc(&self, key: &AES128) -> Self::E {
+        self.created.set(self.created.get() + 1);
+        DefaultFactory.new_enc(key)
+    }
+
+    fn new_dec(&self, key: &AES128) -> Self::D {
+        self.created.set(self.created.get() + 1);
+        DefaultFactory.new_dec(key)
+    }
+
+    fn new_mac(&self, key: &AES128) -> Self::M {
+        self.created.set(self.created.get() + 1);
+        DefaultFactory.new_mac(key)
+    }
+}
+
+#[test]
+fn test_crypto_factory_instance_is_used() {
+    use lorawan::default_crypto::Crypto;
+
+    let hardware = CountingFactory::default();
+    let mut device: Device<_, _, _, 255> = Device::new_with_crypto(
+        region::Configuration::new(region::Region::US915),
+        TestRadio::default(),
+        rand::rngs::OsRng,
+        Crypto::Hardware(&hardware),
+    );
+    device.join(get_abp_credentials()).unwrap();
+    device.send(&[0; 1], 1, true).unwrap();
+    let sent = hardware.created.get();
+    assert!(sent > 0);
+
+    device.handle_event(Event::TimeoutFired).unwrap(); // begin Rx1
+    device.get_radio().set_rxtx_handler(handle_data_uplink_with_link_adr_req::<0, 0>);
+    let response = device.handle_event(Event::RadioEvent(radio::Event::Phy(()))).unwrap();
+    assert!(matches!(response, Response::DownlinkReceived(0)));
+    assert!(hardware.created.get() > sent);
+}
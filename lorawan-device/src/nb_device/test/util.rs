@@ -20,12 +20,18 @@ pub struct TestRadio {
     rxtx_handler: Option<RxTxHandler>,
     buffer: [u8; 256],
     buffer_index: usize,
+    rx_quality: RxQuality,
 }
 
 impl TestRadio {
     pub fn set_rxtx_handler(&mut self, handler: RxTxHandler) {
         self.rxtx_handler = Some(handler);
     }
+
+    /// Sets the RSSI and SNR reported for the received frames.
+    pub fn set_rx_quality(&mut self, rx_quality: RxQuality) {
+        self.rx_quality = rx_quality;
+    }
 }
 
 impl Default for TestRadio {
@@ -36,6 +42,7 @@ impl Default for TestRadio {
             rxtx_handler: None,
             buffer: [0; 256],
             buffer_index: 0,
+            rx_quality: RxQuality::new(0, 0),
         }
     }
 }
@@ -80,7 +87,7 @@ impl PhyRxTx for TestRadio {
                 {
                     self.buffer_index =
                         rxtx_handler(self.last_uplink.take(), rf_config, &mut self.buffer);
-                    return Ok(Response::RxDone(RxQuality::new(0, 0)));
+                    return Ok(Response::RxDone(self.rx_quality));
                 }
             }
         }
@@ -29,6 +29,10 @@ impl<const DEFAULT_RX2: u32, const OFFSET: u32> DynamicChannelRegion<2, 7>
     fn get_default_rx2() -> u32 {
         DEFAULT_RX2
     }
+
+    fn frequency_range() -> (u32, u32) {
+        (915_000_000, 928_000_000)
+    }
 }
 
 use super::{Bandwidth, Datarate, SpreadingFactor};
@@ -23,6 +23,10 @@ impl DynamicChannelRegion<3, 7> for EU433Region {
     fn get_default_rx2() -> u32 {
         434_665_000
     }
+
+    fn frequency_range() -> (u32, u32) {
+        (433_175_000, 434_665_000)
+    }
 }
 
 use super::{Bandwidth, Datarate, SpreadingFactor};
@@ -23,6 +23,10 @@ impl DynamicChannelRegion<3, 7> for EU868Region {
     fn get_default_rx2() -> u32 {
         869_525_000
     }
+
+    fn frequency_range() -> (u32, u32) {
+        (863_000_000, 870_000_000)
+    }
 }
 
 use super::{Bandwidth, Datarate, SpreadingFactor};
@@ -23,6 +23,10 @@ impl DynamicChannelRegion<3, 6> for IN865Region {
     fn get_default_rx2() -> u32 {
         866_550_000
     }
+
+    fn frequency_range() -> (u32, u32) {
+        (865_000_000, 867_000_000)
+    }
 }
 
 use super::{Bandwidth, Datarate, SpreadingFactor};
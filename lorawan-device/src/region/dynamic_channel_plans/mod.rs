@@ -55,7 +55,7 @@ impl<
         if channel < NUM_JOIN_CHANNELS {
             Some(R::join_channels()[channel])
         } else {
-            self.additional_channels[channel - NUM_JOIN_CHANNELS]
+            self.additional_channels.get(channel - NUM_JOIN_CHANNELS).copied().flatten()
         }
     }
 
@@ -91,6 +91,8 @@ pub(crate) trait DynamicChannelRegion<const NUM_JOIN_CHANNELS: usize, const NUM_
 {
     fn join_channels() -> [u32; NUM_JOIN_CHANNELS];
     fn get_default_rx2() -> u32;
+    /// Lowest and highest frequency of the band the region operates in.
+    fn frequency_range() -> (u32, u32);
 }
 
 impl<
@@ -102,7 +104,8 @@ impl<
     fn process_join_accept<T: AsRef<[u8]>, C>(
         &mut self,
         join_accept: &DecryptedJoinAcceptPayload<T, C>,
-    ) {
+    ) -> bool {
+        let additional_channels = self.additional_channels;
         match join_accept.c_f_list() {
             Some(CfList::DynamicChannel(cf_list)) => {
                 // If CfList of Type 0 is present, it may contain up to 5 frequencies
@@ -123,6 +126,16 @@ impl<
             }
             None => {}
         }
+        additional_channels != self.additional_channels
+    }
+
+    fn set_dl_settings(&mut self, rx1_dr_offset: u8, rx2_data_rate: u8) -> bool {
+        let previous = (self.rx1_offset, self.rx2_dr);
+        self.rx1_offset = rx1_dr_offset as usize;
+        if matches!(R::datarates().get(rx2_data_rate as usize), Some(Some(_))) {
+            self.rx2_dr = rx2_data_rate as usize;
+        }
+        previous != (self.rx1_offset, self.rx2_dr)
     }
 
     fn handle_link_adr_channel_mask(
@@ -161,6 +174,43 @@ impl<
         }
     }
 
+    fn has_enabled_channel(&self) -> bool {
+        (0..NUM_JOIN_CHANNELS + 5).any(|channel| {
+            self.channel_mask.is_enabled(channel).unwrap() && self.get_channel(channel).is_some()
+        })
+    }
+
+    fn handle_new_channel(
+        &mut self,
+        index: u8,
+        frequency: u32,
+        data_rate_range: DataRateRange,
+    ) -> (bool, bool) {
+        // default (join) channels cannot be modified
+        let Some(channel) = usize::from(index)
+            .checked_sub(NUM_JOIN_CHANNELS)
+            .and_then(|i| self.additional_channels.get_mut(i))
+        else {
+            return (false, false);
+        };
+        // a frequency of 0 disables the channel, in which case the data rate range is ignored
+        if frequency == 0 {
+            *channel = None;
+            return (true, true);
+        }
+        let (min_frequency, max_frequency) = R::frequency_range();
+        let channel_frequency_ack = (min_frequency..=max_frequency).contains(&frequency);
+        let max_dr = usize::from(data_rate_range.max_data_rate());
+        let data_rate_range_ack = data_rate_range.min_data_rate()
+            <= data_rate_range.max_data_rate()
+            && max_dr < NUM_DATARATES
+            && R::datarates()[max_dr].is_some();
+        if channel_frequency_ack && data_rate_range_ack {
+            *channel = Some(frequency);
+        }
+        (channel_frequency_ack, data_rate_range_ack)
+    }
+
     fn get_tx_dr_and_frequency<RNG: RngCore>(
         &mut self,
         rng: &mut RNG,
@@ -205,11 +255,34 @@ impl<
         }
     }
 
-    fn get_rx_datarate(&self, tx_datarate: DR, _frame: &Frame, window: &Window) -> Datarate {
+    fn get_rx_datarate(&self, tx_datarate: DR, frame: &Frame, window: &Window) -> Datarate {
+        // a JoinAccept is always received with the default settings
+        let (rx1_offset, rx2_dr) = match frame {
+            Frame::Join => (0, 0),
+            Frame::Data => (self.rx1_offset, self.rx2_dr),
+        };
         let datarate = match window {
-            Window::_1 => tx_datarate as usize + self.rx1_offset,
-            Window::_2 => self.rx2_dr,
+            Window::_1 => (tx_datarate as usize).saturating_sub(rx1_offset),
+            Window::_2 => rx2_dr,
         };
         R::datarates()[datarate].clone().unwrap()
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    #[cfg(feature = "region-eu868")]
+    fn test_new_channel_out_of_band() {
+        let mut plan = EU868::default();
+        let data_rate_range = || DataRateRange::from_min_max(0, 5).unwrap();
+        assert_eq!(plan.handle_new_channel(3, 867_100_000, data_rate_range()), (true, true));
+        // 433.175 MHz is in the EU433 band
+        assert_eq!(plan.handle_new_channel(3, 433_175_000, data_rate_range()), (false, true));
+        assert_eq!(plan.handle_new_channel(4, 433_175_000, data_rate_range()), (false, true));
+        assert_eq!(plan.get_channel(3), Some(867_100_000));
+        assert_eq!(plan.get_channel(4), None);
+    }
+}
@@ -58,23 +58,20 @@ impl FixedChannelRegion<16> for AU915Region {
     fn get_default_rx2() -> u32 {
         DEFAULT_RX2
     }
-    fn get_rx_datarate(tx_datarate: DR, _frame: &Frame, window: &Window) -> Datarate {
+    fn get_rx_datarate(
+        tx_datarate: DR,
+        rx1_dr_offset: u8,
+        _frame: &Frame,
+        window: &Window,
+    ) -> Datarate {
         let datarate = match window {
-            Window::_1 => {
-                // no support for RX1 DR Offset
-                match tx_datarate {
-                    DR::_0 => DR::_8,
-                    DR::_1 => DR::_9,
-                    DR::_2 => DR::_10,
-                    DR::_3 => DR::_11,
-                    DR::_4 => DR::_12,
-                    DR::_5 => DR::_13,
-                    DR::_6 => DR::_13,
-                    DR::_7 => DR::_9,
-                    _ => panic!("Invalid TX datarate"),
-                }
-            }
-            Window::_2 => DR::_8,
+            Window::_1 => match tx_datarate as u8 {
+                // DR0 to DR6 map to DR8 to DR13, lowered by the offset down to DR8
+                tx_datarate @ 0..=6 => (8 + tx_datarate).saturating_sub(rx1_dr_offset).clamp(8, 13),
+                7 => 9u8.saturating_sub(rx1_dr_offset).max(8),
+                _ => panic!("Invalid TX datarate"),
+            },
+            Window::_2 => DR::_8 as u8,
         };
         DATARATES[datarate as usize].clone().unwrap()
     }
@@ -216,6 +216,7 @@ mod test {
     use crate::mac::Response;
     use crate::{
         mac::{Mac, SendData},
+        radio::RxQuality,
         test_util::{get_key, handle_join_request, Uplink},
         AppEui, AppKey, DevEui, NetworkCredentials,
     };
@@ -300,15 +301,18 @@ mod test {
         let mut mac = Mac::new(us915.into(), 21, 2);
 
         let mut buf: RadioBuffer<255> = RadioBuffer::new();
-        let (tx_config, _len) = mac.join_otaa::<DefaultFactory, _, 255>(
-            &mut rand::rngs::OsRng,
-            NetworkCredentials::new(
-                AppEui::from([0x0; 8]),
-                DevEui::from([0x0; 8]),
-                AppKey::from(get_key()),
-            ),
-            &mut buf,
-        );
+        let (tx_config, _len) = mac
+            .join_otaa::<DefaultFactory, _, 255>(
+                &DefaultFactory,
+                &mut rand::rngs::OsRng,
+                NetworkCredentials::new(
+                    AppEui::from([0x0; 8]),
+                    DevEui::from([0x0; 8]),
+                    AppKey::from(get_key()),
+                ),
+                &mut buf,
+            )
+            .unwrap();
         // Confirm that the join request occurs on our subband
         assert!(
             tx_config.rf.frequency >= 903_900_000,
@@ -329,13 +333,19 @@ mod test {
         let len = handle_join_request::<0>(Some(uplink), tx_config.rf, &mut rx_buf);
         buf.clear();
         buf.extend_from_slice(&rx_buf[..len]).unwrap();
-        let response = mac.handle_rx::<DefaultFactory, 255, 3>(&mut buf, &mut downlinks);
+        let response = mac.handle_rx::<DefaultFactory, 255, 3>(
+            &DefaultFactory,
+            &mut buf,
+            RxQuality::new(0, 0),
+            &mut downlinks,
+        );
         if let Response::JoinSuccess = response {
         } else {
             panic!("Did not receive join success");
         }
         let (tx_config, _len) = mac
             .send::<DefaultFactory, _, 255>(
+                &DefaultFactory,
                 &mut rand::rngs::OsRng,
                 &mut buf,
                 &SendData { fport: 1, data: &[0x0; 1], confirmed: false },
@@ -361,15 +371,18 @@ mod test {
         let mut mac = Mac::new(us915.into(), 21, 2);
 
         let mut buf: RadioBuffer<255> = RadioBuffer::new();
-        let (tx_config, _len) = mac.join_otaa::<DefaultFactory, _, 255>(
-            &mut rand::rngs::OsRng,
-            NetworkCredentials::new(
-                AppEui::from([0x0; 8]),
-                DevEui::from([0x0; 8]),
-                AppKey::from(get_key()),
-            ),
-            &mut buf,
-        );
+        let (tx_config, _len) = mac
+            .join_otaa::<DefaultFactory, _, 255>(
+                &DefaultFactory,
+                &mut rand::rngs::OsRng,
+                NetworkCredentials::new(
+                    AppEui::from([0x0; 8]),
+                    DevEui::from([0x0; 8]),
+                    AppKey::from(get_key()),
+                ),
+                &mut buf,
+            )
+            .unwrap();
         // Confirm that the join request occurs on our subband
         assert!(
             tx_config.rf.frequency >= 903_900_000,
@@ -390,7 +403,12 @@ mod test {
         let len = handle_join_request::<0>(Some(uplink), tx_config.rf, &mut rx_buf);
         buf.clear();
         buf.extend_from_slice(&rx_buf[..len]).unwrap();
-        let response = mac.handle_rx::<DefaultFactory, 255, 3>(&mut buf, &mut downlinks);
+        let response = mac.handle_rx::<DefaultFactory, 255, 3>(
+            &DefaultFactory,
+            &mut buf,
+            RxQuality::new(0, 0),
+            &mut downlinks,
+        );
         if let Response::JoinSuccess = response {
         } else {
             panic!("Did not receive JoinSuccess")
@@ -398,6 +416,7 @@ mod test {
         for _ in 0..8 {
             let (tx_config, _len) = mac
                 .send::<DefaultFactory, _, 255>(
+                    &DefaultFactory,
                     &mut rand::rngs::OsRng,
                     &mut buf,
                     &SendData { fport: 1, data: &[0x0; 1], confirmed: false },
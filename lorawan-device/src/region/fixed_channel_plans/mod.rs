@@ -44,6 +44,8 @@ pub(crate) struct FixedChannelPlan<const NUM_DR: usize, F: FixedChannelRegion<NU
     channel_mask: ChannelMask<9>,
     _fixed_channel_region: PhantomData<F>,
     join_channels: JoinChannels,
+    rx1_dr_offset: u8,
+    rx2_dr: Option<u8>,
 }
 
 impl<const D: usize, F: FixedChannelRegion<D>> FixedChannelPlan<D, F> {
@@ -73,7 +75,12 @@ pub(crate) trait FixedChannelRegion<const D: usize>: ChannelRegion<D> {
     fn uplink_channels() -> &'static [u32; 72];
     fn downlink_channels() -> &'static [u32; 8];
     fn get_default_rx2() -> u32;
-    fn get_rx_datarate(tx_datarate: DR, frame: &Frame, window: &Window) -> Datarate;
+    fn get_rx_datarate(
+        tx_datarate: DR,
+        rx1_dr_offset: u8,
+        frame: &Frame,
+        window: &Window,
+    ) -> Datarate;
     fn get_dbm() -> i8;
 }
 
@@ -81,12 +88,25 @@ impl<const D: usize, F: FixedChannelRegion<D>> RegionHandler for FixedChannelPla
     fn process_join_accept<T: AsRef<[u8]>, C>(
         &mut self,
         join_accept: &DecryptedJoinAcceptPayload<T, C>,
-    ) {
+    ) -> bool {
         if let Some(CfList::FixedChannel(channel_mask)) = join_accept.c_f_list() {
             // Reset the join channels state
             self.join_channels.reset();
+            let changed = self.channel_mask != channel_mask;
             self.channel_mask = channel_mask;
+            changed
+        } else {
+            false
+        }
+    }
+
+    fn set_dl_settings(&mut self, rx1_dr_offset: u8, rx2_data_rate: u8) -> bool {
+        let previous = (self.rx1_dr_offset, self.rx2_dr);
+        self.rx1_dr_offset = rx1_dr_offset;
+        if matches!(F::datarates().get(rx2_data_rate as usize), Some(Some(_))) {
+            self.rx2_dr = Some(rx2_data_rate);
         }
+        previous != (self.rx1_dr_offset, self.rx2_dr)
     }
 
     fn handle_link_adr_channel_mask(
@@ -96,29 +116,38 @@ impl<const D: usize, F: FixedChannelRegion<D>> RegionHandler for FixedChannelPla
     ) {
         self.join_channels.reset();
         match channel_mask_control {
-            0..=4 => {
+            0..=3 => {
                 let base_index = channel_mask_control as usize * 2;
                 self.channel_mask.set_bank(base_index, channel_mask.get_index(0));
                 self.channel_mask.set_bank(base_index + 1, channel_mask.get_index(1));
             }
+            4 => {
+                // only channels 64 to 71 exist
+                self.channel_mask.set_bank(8, channel_mask.get_index(0));
+            }
             5 => {
-                let channel_mask: u16 =
-                    channel_mask.get_index(0) as u16 | ((channel_mask.get_index(1) as u16) << 8);
-                self.channel_mask.set_bank(0, ((channel_mask & 0b1) * 0xFF) as u8);
-                self.channel_mask.set_bank(1, ((channel_mask & 0b10) * 0xFF) as u8);
-                self.channel_mask.set_bank(2, ((channel_mask & 0b100) * 0xFF) as u8);
-                self.channel_mask.set_bank(3, ((channel_mask & 0b1000) * 0xFF) as u8);
-                self.channel_mask.set_bank(4, ((channel_mask & 0b10000) * 0xFF) as u8);
-                self.channel_mask.set_bank(5, ((channel_mask & 0b100000) * 0xFF) as u8);
-                self.channel_mask.set_bank(6, ((channel_mask & 0b1000000) * 0xFF) as u8);
-                self.channel_mask.set_bank(7, ((channel_mask & 0b10000000) * 0xFF) as u8);
-                self.channel_mask.set_bank(8, ((channel_mask & 0b100000000) * 0xFF) as u8);
+                // each bit enables a sub-band of 8 125 kHz channels along with its 500 kHz channel
+                let sub_bands = channel_mask.get_index(0);
+                for sub_band in 0..8 {
+                    let enabled = sub_bands & (1 << sub_band) != 0;
+                    self.channel_mask.set_bank(
+                        sub_band,
+                        if enabled {
+                            0xFF
+                        } else {
+                            0x00
+                        },
+                    );
+                    self.channel_mask.set_channel(64 + sub_band, enabled);
+                }
             }
             6 => {
                 self.set_125k_channels(true);
+                self.channel_mask.set_bank(8, channel_mask.get_index(0));
             }
             7 => {
                 self.set_125k_channels(false);
+                self.channel_mask.set_bank(8, channel_mask.get_index(0));
             }
             _ => {
                 //RFU
@@ -126,6 +155,10 @@ impl<const D: usize, F: FixedChannelRegion<D>> RegionHandler for FixedChannelPla
         }
     }
 
+    fn has_enabled_channel(&self) -> bool {
+        (0..72).any(|channel| self.channel_mask.is_enabled(channel).unwrap())
+    }
+
     fn get_tx_dr_and_frequency<RNG: RngCore>(
         &mut self,
         rng: &mut RNG,
@@ -201,6 +234,88 @@ impl<const D: usize, F: FixedChannelRegion<D>> RegionHandler for FixedChannelPla
     }
 
     fn get_rx_datarate(&self, tx_datarate: DR, frame: &Frame, window: &Window) -> Datarate {
-        F::get_rx_datarate(tx_datarate, frame, window)
+        match (frame, window, self.rx2_dr) {
+            // a JoinAccept is always received with the default settings
+            (Frame::Join, _, _) => F::get_rx_datarate(tx_datarate, 0, frame, window),
+            (Frame::Data, Window::_2, Some(rx2_dr)) => {
+                F::datarates()[rx2_dr as usize].clone().unwrap()
+            }
+            (Frame::Data, _, _) => {
+                F::get_rx_datarate(tx_datarate, self.rx1_dr_offset, frame, window)
+            }
+        }
+    }
+}
+
+#[cfg(all(test, feature = "region-us915"))]
+mod test {
+    use super::*;
+
+    fn link_adr_channel_mask(channel_mask_control: u8, mask: [u8; 2]) -> ChannelMask<9> {
+        let mut plan = US915::new().0;
+        plan.handle_link_adr_channel_mask(channel_mask_control, ChannelMask::from(mask));
+        plan.channel_mask
+    }
+
+    fn channel_mask(configuration: &Configuration) -> ChannelMask<9> {
+        match &configuration.state {
+            State::US915(us915) => us915.0.channel_mask.clone(),
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn test_link_adr_block_combines_channel_masks() {
+        let mut configuration: Configuration = US915::new().into();
+        // disabling all channels first is fine as long as the block enables some in the end
+        let channel_mask_ack = configuration.set_channel_masks([
+            (7, ChannelMask::from([0, 0])),
+            (0, ChannelMask::from([0x00, 0xFF])),
+            (4, ChannelMask::from([0b10, 0])),
+        ]);
+        assert!(channel_mask_ack);
+        let mask = channel_mask(&configuration);
+        for c in 0..72 {
+            let expected = (8..16).contains(&c) || c == 65;
+            assert_eq!(mask.is_enabled(c).unwrap(), expected, "channel {c}");
+        }
+    }
+
+    #[test]
+    fn test_link_adr_block_disabling_all_channels_rejected() {
+        let mut configuration: Configuration = US915::new().into();
+        let channel_mask_ack = configuration
+            .set_channel_masks([(7, ChannelMask::from([0, 0])), (0, ChannelMask::from([0, 0]))]);
+        assert!(!channel_mask_ack);
+        // the channel mask is left untouched
+        let mask = channel_mask(&configuration);
+        assert!((0..72).all(|c| mask.is_enabled(c).unwrap()));
+    }
+
+    #[test]
+    fn test_link_adr_enable_500khz_channel() {
+        let mask = link_adr_channel_mask(4, [0b0001_0000, 0]);
+        assert!(mask.is_enabled(68).unwrap());
+        assert!(!mask.is_enabled(64).unwrap());
+        assert!(!mask.is_enabled(71).unwrap());
+        // 125 kHz channels are left untouched
+        assert!((0..64).all(|c| mask.is_enabled(c).unwrap()));
+    }
+
+    #[test]
+    fn test_link_adr_select_sub_band() {
+        let mask = link_adr_channel_mask(5, [0b0000_0010, 0]);
+        for c in 0..72 {
+            let expected = (8..16).contains(&c) || c == 65;
+            assert_eq!(mask.is_enabled(c).unwrap(), expected, "channel {c}");
+        }
+    }
+
+    #[test]
+    fn test_link_adr_all_125khz_channels_off() {
+        let mask = link_adr_channel_mask(7, [0b0000_0001, 0]);
+        for c in 0..72 {
+            assert_eq!(mask.is_enabled(c).unwrap(), c == 64, "channel {c}");
+        }
     }
 }
@@ -58,20 +58,21 @@ impl FixedChannelRegion<14> for US915Region {
     fn get_default_rx2() -> u32 {
         DEFAULT_RX2
     }
-    fn get_rx_datarate(tx_datarate: DR, _frame: &Frame, window: &Window) -> Datarate {
+    fn get_rx_datarate(
+        tx_datarate: DR,
+        rx1_dr_offset: u8,
+        _frame: &Frame,
+        window: &Window,
+    ) -> Datarate {
         let datarate = match window {
             Window::_1 => {
-                // no support for RX1 DR Offset
-                match tx_datarate {
-                    DR::_0 => DR::_10,
-                    DR::_1 => DR::_11,
-                    DR::_2 => DR::_12,
-                    DR::_3 => DR::_13,
-                    DR::_4 => DR::_13,
-                    _ => panic!("Invalid TX datarate"),
+                if tx_datarate as u8 > 4 {
+                    panic!("Invalid TX datarate");
                 }
+                // DR0 to DR4 map to DR10 to DR13, lowered by the offset down to DR8
+                (10 + tx_datarate as u8).saturating_sub(rx1_dr_offset).clamp(8, 13)
             }
-            Window::_2 => DR::_8,
+            Window::_2 => DR::_8 as u8,
         };
         DATARATES[datarate as usize].clone().unwrap()
     }
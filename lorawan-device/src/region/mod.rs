@@ -1,6 +1,6 @@
 //! LoRaWAN device region definitions (eg: EU868, US915, etc).
 use lora_modulation::{Bandwidth, BaseBandModulationParams, CodingRate, SpreadingFactor};
-use lorawan::{maccommands::ChannelMask, parser::CfList};
+use lorawan::{maccommands::ChannelMask, parser::CfList, types::DataRateRange};
 use rand_core::RngCore;
 
 use crate::mac::{Frame, Window};
@@ -127,6 +127,30 @@ pub enum Region {
     US915,
 }
 
+impl Region {
+    /// Regions enabled in this build by their `region-*` feature.
+    pub const SUPPORTED: &'static [Region] = &[
+        #[cfg(feature = "region-as923-1")]
+        Region::AS923_1,
+        #[cfg(feature = "region-as923-2")]
+        Region::AS923_2,
+        #[cfg(feature = "region-as923-3")]
+        Region::AS923_3,
+        #[cfg(feature = "region-as923-4")]
+        Region::AS923_4,
+        #[cfg(feature = "region-au915")]
+        Region::AU915,
+        #[cfg(feature = "region-eu868")]
+        Region::EU868,
+        #[cfg(feature = "region-eu433")]
+        Region::EU433,
+        #[cfg(feature = "region-in865")]
+        Region::IN865,
+        #[cfg(feature = "region-us915")]
+        Region::US915,
+    ];
+}
+
 #[derive(Clone)]
 enum State {
     #[cfg(feature = "region-as923-1")]
@@ -173,7 +197,6 @@ impl State {
         }
     }
 
-    #[allow(dead_code)]
     pub fn region(&self) -> Region {
         match self {
             #[cfg(feature = "region-as923-1")]
@@ -416,18 +439,44 @@ impl Configuration {
     pub(crate) fn process_join_accept<T: AsRef<[u8]>, C>(
         &mut self,
         join_accept: &DecryptedJoinAcceptPayload<T, C>,
-    ) {
+    ) -> bool {
         mut_region_dispatch!(self, process_join_accept, join_accept)
     }
 
-    pub(crate) fn set_channel_mask(
-        &mut self,
-        channel_mask_control: u8,
-        channel_mask: ChannelMask<2>,
-    ) {
+    pub(crate) fn set_dl_settings(&mut self, rx1_dr_offset: u8, rx2_data_rate: u8) -> bool {
+        mut_region_dispatch!(self, set_dl_settings, rx1_dr_offset, rx2_data_rate)
+    }
+
+    fn set_channel_mask(&mut self, channel_mask_control: u8, channel_mask: ChannelMask<2>) {
         mut_region_dispatch!(self, handle_link_adr_channel_mask, channel_mask_control, channel_mask)
     }
 
+    /// Applies the channel masks of a block of contiguous LinkADRReqs. They are combined and only
+    /// applied if at least one channel remains enabled, in which case `true` is returned.
+    pub(crate) fn set_channel_masks(
+        &mut self,
+        channel_masks: impl IntoIterator<Item = (u8, ChannelMask<2>)>,
+    ) -> bool {
+        let mut combined = self.clone();
+        for (channel_mask_control, channel_mask) in channel_masks {
+            combined.set_channel_mask(channel_mask_control, channel_mask);
+        }
+        let channel_mask_ack = region_dispatch!(combined, has_enabled_channel);
+        if channel_mask_ack {
+            *self = combined;
+        }
+        channel_mask_ack
+    }
+
+    pub(crate) fn handle_new_channel(
+        &mut self,
+        index: u8,
+        frequency: u32,
+        data_rate_range: DataRateRange,
+    ) -> (bool, bool) {
+        mut_region_dispatch!(self, handle_new_channel, index, frequency, data_rate_range)
+    }
+
     pub(crate) fn get_rx_frequency(&self, frame: &Frame, window: &Window) -> u32 {
         region_dispatch!(self, get_rx_frequency, frame, window)
     }
@@ -465,10 +514,17 @@ impl Configuration {
         region_dispatch!(self, get_coding_rate)
     }
 
-    #[allow(dead_code)]
-    pub(crate) fn get_current_region(&self) -> super::region::Region {
+    /// Returns the region this configuration was created for.
+    pub fn get_current_region(&self) -> Region {
         self.state.region()
     }
+
+    /// Returns the default RF configuration of the RX2 window of the region, ignoring the changes
+    /// made by the network, eg: with the DLSettings of a JoinAccept.
+    pub fn get_rx2_config(&self) -> RfConfig {
+        let default = Configuration::new(self.get_current_region());
+        default.get_rx_config(default.get_default_datarate(), &Frame::Data, &Window::_2)
+    }
 }
 
 macro_rules! from_region {
@@ -503,10 +559,15 @@ from_region!(US915);
 use lorawan::parser::DecryptedJoinAcceptPayload;
 
 pub(crate) trait RegionHandler {
+    /// Applies the CFList of a JoinAccept, returning whether the channels changed.
     fn process_join_accept<T: AsRef<[u8]>, C>(
         &mut self,
         join_accept: &DecryptedJoinAcceptPayload<T, C>,
-    );
+    ) -> bool;
+
+    /// Sets the RX1 data rate offset and the RX2 data rate of data frames, returning whether they
+    /// changed. An RX2 data rate which does not exist in the region is ignored.
+    fn set_dl_settings(&mut self, rx1_dr_offset: u8, rx2_data_rate: u8) -> bool;
 
     fn handle_link_adr_channel_mask(
         &mut self,
@@ -514,6 +575,21 @@ pub(crate) trait RegionHandler {
         channel_mask: ChannelMask<2>,
     );
 
+    /// Whether at least one uplink channel is enabled by the channel mask.
+    fn has_enabled_channel(&self) -> bool;
+
+    /// Handles a NewChannelReq, returning whether the frequency and the data rate range were
+    /// accepted. The channel is only modified if both are.
+    fn handle_new_channel(
+        &mut self,
+        _index: u8,
+        _frequency: u32,
+        _data_rate_range: DataRateRange,
+    ) -> (bool, bool) {
+        // NewChannelReq is not supported by fixed channel plans
+        (false, false)
+    }
+
     fn get_default_datarate(&self) -> DR {
         DR::_0
     }
@@ -533,3 +609,61 @@ pub(crate) trait RegionHandler {
         DEFAULT_CODING_RATE
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    #[cfg(feature = "region-us915")]
+    fn test_runtime_region_rx2_defaults() {
+        let configuration = Configuration::new(Region::US915);
+        assert_eq!(configuration.get_current_region(), Region::US915);
+        let rx2 = configuration.get_rx2_config();
+        assert_eq!(rx2.frequency, 923_300_000);
+        assert_eq!(rx2.bb.sf, SpreadingFactor::_12);
+        assert_eq!(rx2.bb.bw, Bandwidth::_500KHz);
+
+        // the defaults are kept when the network changes the RX2 data rate
+        let mut configuration = configuration;
+        assert!(configuration.set_dl_settings(0, 10));
+        assert_eq!(configuration.get_rx2_config(), rx2);
+        let current = configuration.get_rx_config(DR::_0, &Frame::Data, &Window::_2);
+        assert_eq!(current.bb.sf, SpreadingFactor::_10);
+    }
+
+    #[test]
+    fn test_supported_regions() {
+        // exactly the regions whose feature is enabled
+        let enabled = [
+            cfg!(feature = "region-as923-1"),
+            cfg!(feature = "region-as923-2"),
+            cfg!(feature = "region-as923-3"),
+            cfg!(feature = "region-as923-4"),
+            cfg!(feature = "region-au915"),
+            cfg!(feature = "region-eu868"),
+            cfg!(feature = "region-eu433"),
+            cfg!(feature = "region-in865"),
+            cfg!(feature = "region-us915"),
+        ];
+        assert_eq!(Region::SUPPORTED.len(), enabled.iter().filter(|enabled| **enabled).count());
+        #[cfg(feature = "region-as923-1")]
+        assert!(Region::SUPPORTED.contains(&Region::AS923_1));
+        #[cfg(feature = "region-as923-2")]
+        assert!(Region::SUPPORTED.contains(&Region::AS923_2));
+        #[cfg(feature = "region-as923-3")]
+        assert!(Region::SUPPORTED.contains(&Region::AS923_3));
+        #[cfg(feature = "region-as923-4")]
+        assert!(Region::SUPPORTED.contains(&Region::AS923_4));
+        #[cfg(feature = "region-au915")]
+        assert!(Region::SUPPORTED.contains(&Region::AU915));
+        #[cfg(feature = "region-eu868")]
+        assert!(Region::SUPPORTED.contains(&Region::EU868));
+        #[cfg(feature = "region-eu433")]
+        assert!(Region::SUPPORTED.contains(&Region::EU433));
+        #[cfg(feature = "region-in865")]
+        assert!(Region::SUPPORTED.contains(&Region::IN865));
+        #[cfg(feature = "region-us915")]
+        assert!(Region::SUPPORTED.contains(&Region::US915));
+    }
+}
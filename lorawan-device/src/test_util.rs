@@ -6,7 +6,10 @@ use lorawan::parser::{self, DataHeader};
 use lorawan::{
     default_crypto::DefaultFactory,
     maccommandcreator::LinkADRReqCreator,
-    maccommands::LinkADRReqPayload,
+    maccommands::{
+        DevStatusReqPayload, DeviceTimeAnsPayload, DeviceTimeReqPayload, LinkADRReqPayload,
+        LinkCheckAnsPayload, LinkCheckReqPayload, NewChannelReqPayload, RXTimingSetupReqPayload,
+    },
     parser::{parse, DataPayload, JoinAcceptPayload, PhyPayload},
 };
 use mac::Session;
@@ -269,3 +272,321 @@ pub fn class_c_downlink<const FCNT_DOWN: u32>(
         phy.build(&[1, 2, 3], &[], &get_key().into(), &get_key().into(), &DefaultFactory).unwrap();
     finished.len()
 }
+
+/// Handle an uplink and respond with a downlink whose MIC is computed with the wrong NwkSKey
+pub fn handle_data_uplink_with_bad_mic<const FCNT_DOWN: u32>(
+    _uplink: Option<Uplink>,
+    _config: RfConfig,
+    rx_buffer: &mut [u8],
+) -> usize {
+    let mut phy = lorawan::creator::DataPayloadCreator::new(rx_buffer).unwrap();
+    phy.set_f_port(3);
+    phy.set_dev_addr(&[0; 4]);
+    phy.set_uplink(false);
+    phy.set_fcnt(FCNT_DOWN);
+    let finished =
+        phy.build(&[1, 2, 3], &[], &[1; 16].into(), &get_key().into(), &DefaultFactory).unwrap();
+    finished.len()
+}
+
+/// Handle an uplink and respond with a DevStatusReq in FOpts
+pub fn handle_data_uplink_with_dev_status_req(
+    uplink: Option<Uplink>,
+    _config: RfConfig,
+    rx_buffer: &mut [u8],
+) -> usize {
+    if let Some(mut uplink) = uplink {
+        if let PhyPayload::Data(DataPayload::Encrypted(data)) = uplink.get_payload() {
+            let fcnt = data.fhdr().fcnt() as u32;
+            assert!(data.validate_mic(&get_key().into(), fcnt));
+            let cmd = DownlinkMacCommand::DevStatusReq(DevStatusReqPayload());
+            let cmds: Vec<&dyn SerializableMacCommand> = vec![&cmd];
+            let mut phy = lorawan::creator::DataPayloadCreator::new(rx_buffer).unwrap();
+            phy.set_confirmed(false);
+            phy.set_dev_addr(&[0; 4]);
+            phy.set_uplink(false);
+            phy.set_fcnt(0);
+            let finished = phy
+                .build(&[], &cmds, &get_key().into(), &get_key().into(), &DefaultFactory)
+                .unwrap();
+            finished.len()
+        } else {
+            panic!("Did not decode PhyPayload::Data!");
+        }
+    } else {
+        panic!("No uplink passed to handle_data_uplink_with_dev_status_req");
+    }
+}
+
+/// Handle an uplink which is expected to carry a DevStatusAns with battery level 100 and margin
+/// `MARGIN`
+pub fn handle_data_uplink_with_dev_status_ans<const MARGIN: i8>(
+    uplink: Option<Uplink>,
+    _config: RfConfig,
+    _rx_buffer: &mut [u8],
+) -> usize {
+    if let Some(mut uplink) = uplink {
+        if let PhyPayload::Data(DataPayload::Encrypted(data)) = uplink.get_payload() {
+            let fcnt = data.fhdr().fcnt() as u32;
+            assert!(data.validate_mic(&get_key().into(), fcnt));
+            let fhdr = data.fhdr();
+            let mut cmds = MacCommandIterator::<UplinkMacCommand<'_>>::new(fhdr.data());
+            assert!(matches!(
+                cmds.next(),
+                Some(UplinkMacCommand::DevStatusAns(ans)) if ans.battery() == 100 && ans.margin() == MARGIN
+            ));
+            assert!(cmds.next().is_none());
+            0
+        } else {
+            panic!("Did not decode PhyPayload::Data!");
+        }
+    } else {
+        panic!("No uplink passed to handle_data_uplink_with_dev_status_ans");
+    }
+}
+
+/// Handle an uplink which is expected to acknowledge a confirmed downlink
+pub fn handle_data_uplink_with_ack(
+    uplink: Option<Uplink>,
+    _config: RfConfig,
+    _rx_buffer: &mut [u8],
+) -> usize {
+    if let Some(mut uplink) = uplink {
+        if let PhyPayload::Data(DataPayload::Encrypted(data)) = uplink.get_payload() {
+            let fcnt = data.fhdr().fcnt() as u32;
+            assert!(data.validate_mic(&get_key().into(), fcnt));
+            assert!(data.fhdr().fctrl().ack());
+            0
+        } else {
+            panic!("Did not decode PhyPayload::Data!");
+        }
+    } else {
+        panic!("No uplink passed to handle_data_uplink_with_ack");
+    }
+}
+
+/// Handle an uplink which is expected to carry a LinkCheckReq and respond with a LinkCheckAns
+pub fn handle_data_uplink_with_link_check_req(
+    uplink: Option<Uplink>,
+    _config: RfConfig,
+    rx_buffer: &mut [u8],
+) -> usize {
+    if let Some(mut uplink) = uplink {
+        if let PhyPayload::Data(DataPayload::Encrypted(data)) = uplink.get_payload() {
+            let fcnt = data.fhdr().fcnt() as u32;
+            assert!(data.validate_mic(&get_key().into(), fcnt));
+            // LinkCheckReq has no payload, so FOpts consists of the CID only
+            assert_eq!(data.fhdr().data(), &[LinkCheckReqPayload::cid()]);
+            let cmd = DownlinkMacCommand::LinkCheckAns(LinkCheckAnsPayload::from(&[20, 3]));
+            let cmds: Vec<&dyn SerializableMacCommand> = vec![&cmd];
+            let mut phy = lorawan::creator::DataPayloadCreator::new(rx_buffer).unwrap();
+            phy.set_confirmed(false);
+            phy.set_dev_addr(&[0; 4]);
+            phy.set_uplink(false);
+            phy.set_fcnt(0);
+            let finished = phy
+                .build(&[], &cmds, &get_key().into(), &get_key().into(), &DefaultFactory)
+                .unwrap();
+            finished.len()
+        } else {
+            panic!("Did not decode PhyPayload::Data!");
+        }
+    } else {
+        panic!("No uplink passed to handle_data_uplink_with_link_check_req");
+    }
+}
+
+pub fn handle_data_uplink_with_device_time_req(
+    uplink: Option<Uplink>,
+    _config: RfConfig,
+    rx_buffer: &mut [u8],
+) -> usize {
+    if let Some(mut uplink) = uplink {
+        if let PhyPayload::Data(DataPayload::Encrypted(data)) = uplink.get_payload() {
+            let fcnt = data.fhdr().fcnt() as u32;
+            assert!(data.validate_mic(&get_key().into(), fcnt));
+            // DeviceTimeReq has no payload, so FOpts consists of the CID only
+            assert_eq!(data.fhdr().data(), &[DeviceTimeReqPayload::cid()]);
+            let cmd = DownlinkMacCommand::DeviceTimeAns(
+                DeviceTimeAnsPayload::new(&[0x12, 0x34, 0x56, 0x78, 0x80]).unwrap(),
+            );
+            let cmds: Vec<&dyn SerializableMacCommand> = vec![&cmd];
+            let mut phy = lorawan::creator::DataPayloadCreator::new(rx_buffer).unwrap();
+            phy.set_confirmed(false);
+            phy.set_dev_addr(&[0; 4]);
+            phy.set_uplink(false);
+            phy.set_fcnt(0);
+            let finished = phy
+                .build(&[], &cmds, &get_key().into(), &get_key().into(), &DefaultFactory)
+                .unwrap();
+            finished.len()
+        } else {
+            panic!("Did not decode PhyPayload::Data!");
+        }
+    } else {
+        panic!("No uplink passed to handle_data_uplink_with_device_time_req");
+    }
+}
+
+/// Handle an uplink and respond with DevStatusReq, LinkCheckAns and DeviceTimeAns in the
+/// FRMPayload
+pub fn handle_data_uplink_with_frm_payload_mac_commands(
+    uplink: Option<Uplink>,
+    _config: RfConfig,
+    rx_buffer: &mut [u8],
+) -> usize {
+    if let Some(mut uplink) = uplink {
+        if let PhyPayload::Data(DataPayload::Encrypted(data)) = uplink.get_payload() {
+            let fcnt = data.fhdr().fcnt() as u32;
+            assert!(data.validate_mic(&get_key().into(), fcnt));
+            let dev_status_req = DownlinkMacCommand::DevStatusReq(DevStatusReqPayload());
+            let link_check_ans =
+                DownlinkMacCommand::LinkCheckAns(LinkCheckAnsPayload::from(&[20, 3]));
+            let device_time_ans = DownlinkMacCommand::DeviceTimeAns(
+                DeviceTimeAnsPayload::new(&[0x12, 0x34, 0x56, 0x78, 0x80]).unwrap(),
+            );
+            let cmds: Vec<&dyn SerializableMacCommand> =
+                vec![&dev_status_req, &link_check_ans, &device_time_ans];
+            let mut phy = lorawan::creator::DataPayloadCreator::new(rx_buffer).unwrap();
+            phy.set_confirmed(false);
+            phy.set_f_port(0);
+            phy.set_dev_addr(&[0; 4]);
+            phy.set_uplink(false);
+            phy.set_fcnt(0);
+            let finished = phy
+                .build(&[], &cmds, &get_key().into(), &get_key().into(), &DefaultFactory)
+                .unwrap();
+            finished.len()
+        } else {
+            panic!("Did not decode PhyPayload::Data!");
+        }
+    } else {
+        panic!("No uplink passed to handle_data_uplink_with_frm_payload_mac_commands");
+    }
+}
+
+/// Handle an uplink and respond with a LinkADRReq, five NewChannelReq, a DevStatusReq and a
+/// RXTimingSetupReq in the FRMPayload, whose answers exceed the 15 bytes of FOpts
+pub fn handle_data_uplink_with_fopts_overflowing_mac_commands(
+    uplink: Option<Uplink>,
+    _config: RfConfig,
+    rx_buffer: &mut [u8],
+) -> usize {
+    if let Some(mut uplink) = uplink {
+        if let PhyPayload::Data(DataPayload::Encrypted(data)) = uplink.get_payload() {
+            let fcnt = data.fhdr().fcnt() as u32;
+            assert!(data.validate_mic(&get_key().into(), fcnt));
+            let link_adr_req = link_adr_req_with_bank_ctrl(0b10);
+            let link_adr_req = DownlinkMacCommand::LinkADRReq(
+                LinkADRReqPayload::new(&link_adr_req.build()[1..]).unwrap(),
+            );
+            let new_channel_req = DownlinkMacCommand::NewChannelReq(
+                NewChannelReqPayload::new(&[3, 0x18, 0x4e, 0x84, 0x50]).unwrap(),
+            );
+            let dev_status_req = DownlinkMacCommand::DevStatusReq(DevStatusReqPayload());
+            let rx_timing_setup_req =
+                DownlinkMacCommand::RXTimingSetupReq(RXTimingSetupReqPayload::new(&[1]).unwrap());
+            let cmds: Vec<&dyn SerializableMacCommand> = vec![
+                &link_adr_req,
+                &new_channel_req,
+                &new_channel_req,
+                &new_channel_req,
+                &new_channel_req,
+                &new_channel_req,
+                &dev_status_req,
+                &rx_timing_setup_req,
+            ];
+            let mut phy = lorawan::creator::DataPayloadCreator::new(rx_buffer).unwrap();
+            phy.set_confirmed(false);
+            phy.set_f_port(0);
+            phy.set_dev_addr(&[0; 4]);
+            phy.set_uplink(false);
+            phy.set_fcnt(0);
+            let finished = phy
+                .build(&[], &cmds, &get_key().into(), &get_key().into(), &DefaultFactory)
+                .unwrap();
+            finished.len()
+        } else {
+            panic!("Did not decode PhyPayload::Data!");
+        }
+    } else {
+        panic!("No uplink passed to handle_data_uplink_with_fopts_overflowing_mac_commands");
+    }
+}
+
+fn assert_f_opts_cids(uplink: Option<Uplink>, expected: &[u8]) {
+    if let Some(mut uplink) = uplink {
+        if let PhyPayload::Data(DataPayload::Encrypted(data)) = uplink.get_payload() {
+            let fcnt = data.fhdr().fcnt() as u32;
+            assert!(data.validate_mic(&get_key().into(), fcnt));
+            let fhdr = data.fhdr();
+            let cids: Vec<u8> = MacCommandIterator::<UplinkMacCommand<'_>>::new(fhdr.data())
+                .map(|cmd| cmd.cid())
+                .collect();
+            assert_eq!(cids, expected);
+        } else {
+            panic!("Did not decode PhyPayload::Data!");
+        }
+    } else {
+        panic!("No uplink passed to assert_f_opts_cids");
+    }
+}
+
+/// Handle an uplink which is expected to carry the answers to
+/// `handle_data_uplink_with_fopts_overflowing_mac_commands` which fit into FOpts: a LinkADRAns, a
+/// RXTimingSetupAns, a DevStatusAns and four NewChannelAns
+pub fn handle_data_uplink_with_fopts_limited_answers(
+    uplink: Option<Uplink>,
+    _config: RfConfig,
+    _rx_buffer: &mut [u8],
+) -> usize {
+    assert_f_opts_cids(uplink, &[0x03, 0x08, 0x06, 0x07, 0x07, 0x07, 0x07]);
+    0
+}
+
+/// Handle an uplink which is expected to carry the fifth NewChannelAns deferred by
+/// `handle_data_uplink_with_fopts_limited_answers`
+pub fn handle_data_uplink_with_deferred_new_channel_ans(
+    uplink: Option<Uplink>,
+    _config: RfConfig,
+    _rx_buffer: &mut [u8],
+) -> usize {
+    assert_f_opts_cids(uplink, &[0x07]);
+    0
+}
+
+/// The JoinAccept a server would send in response to a JoinRequest in both the RX1 and RX2
+/// windows, along with the RF configuration and delay (from the end of the uplink) of each window.
+pub struct JoinAcceptSchedule {
+    pub rx1_frame: Vec<u8>,
+    pub rx2_frame: Vec<u8>,
+    pub rx1_config: RfConfig,
+    pub rx2_config: RfConfig,
+    /// RX1 and RX2 delays in milliseconds
+    pub delays: (u32, u32),
+}
+
+impl JoinAcceptSchedule {
+    /// Builds the schedule for a JoinRequest sent with `datarate`.
+    pub fn new(join_request: &[u8], region: &region::Configuration, datarate: region::DR) -> Self {
+        let mut join_request = join_request.to_vec();
+        let PhyPayload::JoinRequest(join_request) = parse(join_request.as_mut_slice()).unwrap()
+        else {
+            panic!("Did not parse join request");
+        };
+        assert!(join_request.validate_mic(&get_key().into()));
+        let mut buffer = [0u8; 17];
+        let mut phy = lorawan::creator::JoinAcceptCreator::new(&mut buffer[..]).unwrap();
+        phy.set_app_nonce(&[1; 3]);
+        phy.set_net_id(&[1; 3]);
+        phy.set_dev_addr(get_dev_addr());
+        let frame = phy.build(&get_key().into(), &DefaultFactory).unwrap().to_vec();
+        Self {
+            rx1_frame: frame.clone(),
+            rx2_frame: frame,
+            rx1_config: region.get_rx_config(datarate, &mac::Frame::Join, &mac::Window::_1),
+            rx2_config: region.get_rx_config(datarate, &mac::Frame::Join, &mac::Window::_2),
+            delays: (region::constants::JOIN_ACCEPT_DELAY1, region::constants::JOIN_ACCEPT_DELAY2),
+        }
+    }
+}
@@ -2,7 +2,7 @@
 //!
 //! See [JoinAcceptCreator.new](struct.JoinAcceptCreator.html#method.new) for an example.
 
-use super::keys::{AppKey, AppSKey, CryptoFactory, Decrypter, NwkSKey, AES128};
+use super::keys::{AppKey, AppSKey, CryptoFactory, Decrypter, Mac, NwkSKey, AES128, MIC};
 use super::maccommandcreator;
 use super::maccommands::{mac_commands_len, SerializableMacCommand};
 use super::parser;
@@ -11,7 +11,7 @@ use crate::packet_length::phy::join::{
     JOIN_ACCEPT_LEN, JOIN_ACCEPT_WITH_CFLIST_LEN, JOIN_REQUEST_LEN,
 };
 use crate::packet_length::phy::{MIC_LEN, PHY_PAYLOAD_MIN_LEN};
-use crate::types::{DLSettings, Frequency};
+use crate::types::{ChannelMask, DLSettings, Frequency};
 
 #[derive(Debug, PartialEq)]
 #[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
@@ -23,6 +23,21 @@ pub enum Error {
     FRMPayloadWithFportZero,
 }
 
+impl core::fmt::Display for Error {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let msg = match self {
+            Error::BufferTooShort => "buffer too short",
+            Error::InvalidChannelList => "invalid channel list",
+            Error::MacCommandTooBigForFOpts => "MAC commands too big for FOpts",
+            Error::DataAndMacCommandsInPayloadNotAllowed => {
+                "data and MAC commands in payload not allowed"
+            }
+            Error::FRMPayloadWithFportZero => "FRMPayload data not allowed without FPort",
+        };
+        f.write_str(msg)
+    }
+}
+
 const PIGGYBACK_MAC_COMMANDS_MAX_LEN: usize = 15;
 
 /// JoinAcceptCreator serves for creating binary representation of Physical
@@ -160,7 +175,30 @@ impl<D: AsMut<[u8]>> JoinAcceptCreator<D> {
             d[15 + i * 3] = ((v >> 16) & 0xff) as u8;
         });
         // set cflist type
-        d[JOIN_ACCEPT_WITH_CFLIST_LEN - 1] = 0;
+        d[JOIN_ACCEPT_WITH_CFLIST_LEN - MIC_LEN - 1] = 0;
+        self.with_c_f_list = true;
+
+        Ok(self)
+    }
+
+    /// Sets the CFList of the JoinAccept to a channel mask, as used by regions with fixed
+    /// channel plans.
+    ///
+    /// # Argument
+    ///
+    /// * channel_mask - the channels to be enabled on the device.
+    pub fn set_c_f_list_channel_mask(
+        &mut self,
+        channel_mask: &ChannelMask<9>,
+    ) -> Result<&mut Self, Error> {
+        let d = self.data.as_mut();
+        if d.len() < JOIN_ACCEPT_WITH_CFLIST_LEN {
+            return Err(Error::BufferTooShort);
+        }
+        d[13..22].copy_from_slice(channel_mask.as_ref());
+        d[22..JOIN_ACCEPT_WITH_CFLIST_LEN - MIC_LEN - 1].fill(0);
+        // set cflist type
+        d[JOIN_ACCEPT_WITH_CFLIST_LEN - MIC_LEN - 1] = 1;
         self.with_c_f_list = true;
 
         Ok(self)
@@ -206,6 +244,65 @@ fn set_mic<F: CryptoFactory>(data: &mut [u8], key: &AES128, factory: &F) {
     data[len - MIC_LEN..].copy_from_slice(&mic.0[..]);
 }
 
+/// LoRaWAN version, and the parameters it requires, used for computing a JoinAccept MIC.
+#[derive(Debug)]
+pub enum JoinAcceptMicVersion<'a> {
+    /// LoRaWAN 1.0.x, or LoRaWAN 1.1 with the OptNeg bit unset.
+    V1_0,
+    /// LoRaWAN 1.1 with the OptNeg bit set: the MIC also covers the JoinReqType, the JoinEUI and
+    /// the DevNonce of the request being answered.
+    V1_1 {
+        /// 0xFF for a JoinRequest, 0x00 to 0x02 for the RejoinRequest types.
+        join_req_type: u8,
+        join_eui: &'a parser::EUI64<[u8; 8]>,
+        dev_nonce: &'a parser::DevNonce<[u8; 2]>,
+    },
+}
+
+/// Computes the MIC of a JoinAccept as expected by the end-device.
+///
+/// # Argument
+///
+/// * factory - the crypto factory used for computing the MIC.
+/// * key - the AppKey for LoRaWAN 1.0, the JSIntKey for LoRaWAN 1.1.
+/// * mhdr - the MHDR of the JoinAccept.
+/// * payload - the (decrypted) JoinAccept bytes between the MHDR and the MIC, ie: from the
+///   JoinNonce up to and including the optional CFList.
+/// * version - the LoRaWAN version the MIC is computed for.
+///
+/// # Examples
+///
+/// ```
+/// use lorawan::creator::{join_accept_mic, JoinAcceptMicVersion};
+/// let key = lorawan::keys::AES128([1; 16]);
+/// let payload = [1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 2, 1];
+/// let mic = join_accept_mic(
+///     &lorawan::default_crypto::DefaultFactory,
+///     &key,
+///     lorawan::parser::MHDR::new(0x20),
+///     &payload,
+///     JoinAcceptMicVersion::V1_0,
+/// );
+/// ```
+pub fn join_accept_mic<F: CryptoFactory>(
+    factory: &F,
+    key: &AES128,
+    mhdr: parser::MHDR,
+    payload: &[u8],
+    version: JoinAcceptMicVersion<'_>,
+) -> MIC {
+    let mut mac = factory.new_mac(key);
+    if let JoinAcceptMicVersion::V1_1 { join_req_type, join_eui, dev_nonce } = version {
+        mac.input(&[join_req_type]);
+        mac.input(join_eui.as_ref());
+        mac.input(dev_nonce.as_ref());
+    }
+    mac.input(&[mhdr.into()]);
+    mac.input(payload);
+    let result = mac.result();
+    MIC([result[0], result[1], result[2], result[3]])
+}
+
 /// JoinRequestCreator serves for creating binary representation of Physical
 /// Payload of JoinRequest.
 /// # Examples
@@ -427,9 +524,13 @@ impl<D: AsMut<[u8]>> DataPayloadCreator<D> {
     /// Provides the binary representation of the DataPayload physical payload
     /// with the MIC set and payload encrypted.
     ///
+    /// MAC commands are sent in the FOpts, unless the FPort is set to 0. They then form the
+    /// FRMPayload, which is encrypted with `nwk_skey` and can be longer than the FOpts allow.
+    ///
     /// # Argument
     ///
     /// * payload - the FRMPayload (application) to be sent.
+    /// * cmds - the MAC commands to be sent, either uplink or downlink ones.
     /// * nwk_skey - the key to be used for setting the MIC and possibly for MAC command encryption.
     /// * app_skey - the key to be used for payload encryption if fport not 0, otherwise nwk_skey is
     ///   only used.
@@ -60,6 +60,114 @@ impl Mac for Cmac {
     }
 }
 
+/// Crypto factory selecting at runtime between the software [`DefaultFactory`] and another
+/// (eg: hardware accelerated) implementation, so that a single concrete type can be used where a
+/// [`CryptoFactory`] is expected.
+///
+/// It has no default, as the choice is made at runtime: pass the instance to the device, eg: with
+/// `Device::new_with_crypto` of `lorawan-device`.
+///
+/// # Examples
+///
+/// ```
+/// use lorawan::default_crypto::{Crypto, DefaultFactory};
+/// use lorawan::keys::{CryptoFactory, Mac, AES128};
+///
+/// fn detect_hardware_aes() -> bool {
+///     false
+/// }
+///
+/// // a real application would provide its hardware CryptoFactory here
+/// let crypto: Crypto<DefaultFactory> = if detect_hardware_aes() {
+///     Crypto::Hardware(DefaultFactory)
+/// } else {
+///     Crypto::Software(DefaultFactory)
+/// };
+/// let mut mac = crypto.new_mac(&AES128([1; 16]));
+/// mac.input(&[1, 2, 3]);
+/// let mic = mac.result();
+/// ```
+#[derive(Debug, PartialEq, Eq)]
+pub enum Crypto<H> {
+    Software(DefaultFactory),
+    Hardware(H),
+}
+
+/// Encrypter, decrypter or MAC calculator created by a [`Crypto`] factory.
+#[derive(Debug)]
+pub enum CryptoPrimitive<S, H> {
+    Software(S),
+    Hardware(H),
+}
+
+impl<H: CryptoFactory> CryptoFactory for Crypto<H> {
+    type E = CryptoPrimitive<Aes128, H::E>;
+    type D = CryptoPrimitive<Aes128, H::D>;
+    type M = CryptoPrimitive<Cmac, H::M>;
+
+    fn new_enc(&self, key: &AES128) -> Self::E {
+        match self {
+            Crypto::Software(f) => CryptoPrimitive::Software(f.new_enc(key)),
+            Crypto::Hardware(f) => CryptoPrimitive::Hardware(f.new_enc(key)),
+        }
+    }
+
+    fn new_dec(&self, key: &AES128) -> Self::D {
+        match self {
+            Crypto::Software(f) => CryptoPrimitive::Software(f.new_dec(key)),
+            Crypto::Hardware(f) => CryptoPrimitive::Hardware(f.new_dec(key)),
+        }
+    }
+
+    fn new_mac(&self, key: &AES128) -> Self::M {
+        match self {
+            Crypto::Software(f) => CryptoPrimitive::Software(f.new_mac(key)),
+            Crypto::Hardware(f) => CryptoPrimitive::Hardware(f.new_mac(key)),
+        }
+    }
+}
+
+impl<S: Encrypter, H: Encrypter> Encrypter for CryptoPrimitive<S, H> {
+    fn encrypt_block(&self, block: &mut [u8]) {
+        match self {
+            CryptoPrimitive::Software(e) => e.encrypt_block(block),
+            CryptoPrimitive::Hardware(e) => e.encrypt_block(block),
+        }
+    }
+}
+
+impl<S: Decrypter, H: Decrypter> Decrypter for CryptoPrimitive<S, H> {
+    fn decrypt_block(&self, block: &mut [u8]) {
+        match self {
+            CryptoPrimitive::Software(d) => d.decrypt_block(block),
+            CryptoPrimitive::Hardware(d) => d.decrypt_block(block),
+        }
+    }
+}
+
+impl<S: Mac, H: Mac> Mac for CryptoPrimitive<S, H> {
+    fn input(&mut self, data: &[u8]) {
+        match self {
+            CryptoPrimitive::Software(m) => m.input(data),
+            CryptoPrimitive::Hardware(m) => m.input(data),
+        }
+    }
+
+    fn reset(&mut self) {
+        match self {
+            CryptoPrimitive::Software(m) => m.reset(),
+            CryptoPrimitive::Hardware(m) => m.reset(),
+        }
+    }
+
+    fn result(self) -> [u8; 16] {
+        match self {
+            CryptoPrimitive::Software(m) => m.result(),
+            CryptoPrimitive::Hardware(m) => m.result(),
+        }
+    }
+}
+
 impl<T: AsRef<[u8]>> JoinRequestPayload<T, DefaultFactory> {
     /// Creates a new JoinRequestPayload if the provided data is acceptable.
     ///
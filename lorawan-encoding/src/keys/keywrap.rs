@@ -0,0 +1,83 @@
+//! AES key wrap ([RFC 3394](https://www.rfc-editor.org/rfc/rfc3394)) of a 128-bit key with a
+//! key-encryption key (KEK), eg: to transport keys to or from a provisioning system.
+use super::{CryptoFactory, Decrypter, Encrypter, AES128};
+
+/// Default initial value of RFC 3394, which is checked for integrity on unwrap.
+const IV: [u8; 8] = [0xa6; 8];
+/// Number of 64-bit blocks of the wrapped key.
+const N: usize = 2;
+
+/// Length of a wrapped 128-bit key.
+pub const WRAPPED_LEN: usize = 24;
+
+/// Error returned when the integrity check value of a wrapped key does not match, ie: the KEK is
+/// wrong or the wrapped key was altered.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub struct IntegrityError;
+
+impl core::fmt::Display for IntegrityError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str("key wrap integrity check failed")
+    }
+}
+
+/// Wraps `key` with `kek`.
+pub fn wrap<F: CryptoFactory>(factory: &F, kek: &AES128, key: &AES128) -> [u8; WRAPPED_LEN] {
+    let enc = factory.new_enc(kek);
+    let mut a = IV;
+    let mut r = [[0u8; 8]; N];
+    r[0].copy_from_slice(&key.0[..8]);
+    r[1].copy_from_slice(&key.0[8..]);
+
+    let mut block = [0u8; 16];
+    for j in 0..6 {
+        for (i, r) in r.iter_mut().enumerate() {
+            block[..8].copy_from_slice(&a);
+            block[8..].copy_from_slice(r);
+            enc.encrypt_block(&mut block);
+            let t = (N * j + i + 1) as u64;
+            a = (u64::from_be_bytes(block[..8].try_into().unwrap()) ^ t).to_be_bytes();
+            r.copy_from_slice(&block[8..]);
+        }
+    }
+
+    let mut res = [0u8; WRAPPED_LEN];
+    res[..8].copy_from_slice(&a);
+    res[8..16].copy_from_slice(&r[0]);
+    res[16..].copy_from_slice(&r[1]);
+    res
+}
+
+/// Unwraps a key wrapped with `kek`, checking its integrity.
+pub fn unwrap<F: CryptoFactory>(
+    factory: &F,
+    kek: &AES128,
+    wrapped: &[u8; WRAPPED_LEN],
+) -> Result<AES128, IntegrityError> {
+    let dec = factory.new_dec(kek);
+    let mut a = u64::from_be_bytes(wrapped[..8].try_into().unwrap());
+    let mut r = [[0u8; 8]; N];
+    r[0].copy_from_slice(&wrapped[8..16]);
+    r[1].copy_from_slice(&wrapped[16..]);
+
+    let mut block = [0u8; 16];
+    for j in (0..6).rev() {
+        for (i, r) in r.iter_mut().enumerate().rev() {
+            let t = (N * j + i + 1) as u64;
+            block[..8].copy_from_slice(&(a ^ t).to_be_bytes());
+            block[8..].copy_from_slice(r);
+            dec.decrypt_block(&mut block);
+            a = u64::from_be_bytes(block[..8].try_into().unwrap());
+            r.copy_from_slice(&block[8..]);
+        }
+    }
+
+    if a.to_be_bytes() != IV {
+        return Err(IntegrityError);
+    }
+    let mut key = [0u8; 16];
+    key[..8].copy_from_slice(&r[0]);
+    key[8..].copy_from_slice(&r[1]);
+    Ok(AES128(key))
+}
@@ -0,0 +1,555 @@
+//! Implement types for dealing with LoRaWAN keys and required
+//! cryptography entities.
+use super::parser::{AppNonce, DevNonce, EUI64};
+use subtle::ConstantTimeEq;
+
+pub mod keywrap;
+
+/// Implements serde for a newtype as its hex string (see `Display` and `FromStr`) in
+/// human-readable formats, and like a derived implementation in binary formats.
+#[cfg(feature = "serde")]
+macro_rules! impl_serde_hex {
+    ($type:ident($inner:ty)) => {
+        impl serde::Serialize for $type {
+            fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                if serializer.is_human_readable() {
+                    serializer.collect_str(self)
+                } else {
+                    serializer.serialize_newtype_struct(stringify!($type), &self.0)
+                }
+            }
+        }
+
+        impl<'de> serde::Deserialize<'de> for $type {
+            fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                if deserializer.is_human_readable() {
+                    deserializer.deserialize_str(HexVisitor(core::marker::PhantomData))
+                } else {
+                    #[derive(serde::Deserialize)]
+                    struct $type($inner);
+                    $type::deserialize(deserializer).map(|v| Self(v.0))
+                }
+            }
+        }
+    };
+}
+
+#[cfg(feature = "serde")]
+pub(crate) struct HexVisitor<T>(pub(crate) core::marker::PhantomData<T>);
+
+#[cfg(feature = "serde")]
+impl<T: core::str::FromStr<Err = crate::string::FromHexError>> serde::de::Visitor<'_>
+    for HexVisitor<T>
+{
+    type Value = T;
+
+    fn expecting(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str("a hex string")
+    }
+
+    fn visit_str<E: serde::de::Error>(self, v: &str) -> Result<Self::Value, E> {
+        v.parse().map_err(E::custom)
+    }
+}
+
+macro_rules! lorawan_key {
+    (
+        $(#[$outer:meta])*
+        pub struct $type:ident(AES128);
+    ) => {
+        $(#[$outer])*
+        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        #[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+        pub struct $type(pub(crate) AES128);
+
+        #[cfg(feature = "serde")]
+        impl_serde_hex!($type(AES128));
+
+        impl From<[u8;16]> for $type {
+            fn from(key: [u8; 16]) -> Self {
+                $type(AES128(key))
+            }
+        }
+
+        impl From<$type> for [u8; 16] {
+            fn from(key: $type) -> Self {
+                key.0 .0
+            }
+        }
+
+        impl TryFrom<&[u8]> for $type {
+            type Error = LengthError;
+
+            /// Creates a key from a slice, which must be exactly 16 bytes long.
+            fn try_from(v: &[u8]) -> Result<Self, Self::Error> {
+                <[u8; 16]>::try_from(v).map(Self::from).map_err(|_| LengthError)
+            }
+        }
+
+        impl $type {
+            pub fn inner(&self) -> &AES128 {
+                &self.0
+            }
+
+            /// Gives a reference to the raw key bytes.
+            pub fn as_bytes(&self) -> &[u8; 16] {
+                self.0.as_bytes()
+            }
+
+            /// Converts the key into its raw bytes.
+            pub fn into_bytes(self) -> [u8; 16] {
+                self.0.into_bytes()
+            }
+        }
+
+        #[cfg(feature = "rand")]
+        #[cfg_attr(docsrs, doc(cfg(feature = "rand")))]
+        impl $type {
+            /// Creates a random key, eg: when provisioning an end-device.
+            pub fn generate<R: rand_core::RngCore>(rng: &mut R) -> Self {
+                let mut key = [0u8; 16];
+                rng.fill_bytes(&mut key);
+                Self::from(key)
+            }
+        }
+
+        impl AsRef<[u8]> for $type {
+            fn as_ref(&self) -> &[u8] {
+                &self.0 .0
+            }
+        }
+
+        impl core::ops::Deref for $type {
+            type Target = AES128;
+
+            fn deref(&self) -> &Self::Target {
+                &self.0
+            }
+        }
+    };
+}
+
+lorawan_key!(
+    /// The [`AppKey`] is an AES-128 root key specific to the end-device.
+    ///
+    /// `AppKey` SHALL be stored on an end-device intending to use the OTAA procedure.
+    /// It is NOT REQUIRED for ABP-only end-devices.
+    ///
+    /// To create from a hex-encoded MSB string:
+    /// ```
+    /// use core::str::FromStr;
+    /// use lorawan::keys::AppKey;
+    /// let appkey = AppKey::from_str("00112233445566778899aabbccddeeff").unwrap();
+    /// ```
+    ///
+    /// To create from a byte array, you should enter the bytes in MSB format:
+    /// ```
+    /// use lorawan::keys::AppKey;
+    /// let appkey = AppKey::from([
+    ///     0x00, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88, 0x99, 0xAA, 0xBB, 0xCC, 0xDD, 0xEE,
+    ///     0xFF,
+    /// ]);
+    /// ```
+    pub struct AppKey(AES128);
+);
+lorawan_key!(
+    /// The [`AppSKey`] is an application session key (AES-128) specific to
+    /// the end-device.
+    ///
+    /// `AppSKey` SHOULD be stored such that extraction and re-use by malicious
+    /// actors is prevented.
+    ///
+    /// To create from a hex-encoded MSB string:
+    /// ```
+    /// use core::str::FromStr;
+    /// use lorawan::keys::AppSKey;
+    /// let appskey = AppSKey::from_str("00112233445566778899aabbccddeeff").unwrap();
+    /// ```
+    ///
+    /// To create from a byte array, you should enter the bytes in MSB format:
+    /// ```
+    /// use lorawan::keys::AppSKey;
+    /// let appskey = AppSKey::from([
+    ///     0x00, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88, 0x99, 0xAA, 0xBB, 0xCC, 0xDD, 0xEE,
+    ///     0xFF,
+    /// ]);
+    /// ```
+    pub struct AppSKey(AES128);
+);
+
+lorawan_key!(
+    /// The [`NwkSKey`] is a network session key (AES-128) specific to the end-device.
+    ///
+    /// It SHOULD be stored such that extraction and re-use by malicious
+    /// actors is prevented.
+    ///
+    /// To create from a hex-encoded MSB string:
+    /// ```
+    /// use core::str::FromStr;
+    /// use lorawan::keys::NwkSKey;
+    /// let nwkskey = NwkSKey::from_str("00112233445566778899aabbccddeeff").unwrap();
+    /// ```
+    ///
+    /// To create from a byte array, you should enter the bytes in MSB format:
+    /// ```
+    /// use lorawan::keys::NwkSKey;
+    /// let nwkskey = NwkSKey::from([
+    ///     0x00, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88, 0x99, 0xAA, 0xBB, 0xCC, 0xDD, 0xEE,
+    ///     0xFF,
+    /// ]);
+    /// ```
+    pub struct NwkSKey(AES128);
+);
+
+#[deprecated(since = "0.9.1", note = "Please use `NwkSKey` instead")]
+pub type NewSKey = NwkSKey;
+
+lorawan_key!(
+    /// The [`NwkKey`] is the LoRaWAN 1.1 AES-128 root key from which the network session keys of
+    /// an OTAA session are derived (see [`NwkKey::derive_nwk_s_keys`]).
+    ///
+    /// With LoRaWAN 1.0.x, the network session key is derived from the [`AppKey`] instead.
+    pub struct NwkKey(AES128);
+);
+
+lorawan_key!(
+    /// The [`FNwkSIntKey`] is the LoRaWAN 1.1 forwarding network session integrity key, used to
+    /// compute part of the MIC of uplinks.
+    pub struct FNwkSIntKey(AES128);
+);
+
+lorawan_key!(
+    /// The [`SNwkSIntKey`] is the LoRaWAN 1.1 serving network session integrity key, used to
+    /// compute the MIC of downlinks and part of the MIC of uplinks.
+    pub struct SNwkSIntKey(AES128);
+);
+
+lorawan_key!(
+    /// The [`NwkSEncKey`] is the LoRaWAN 1.1 network session encryption key, used to encrypt MAC
+    /// commands.
+    pub struct NwkSEncKey(AES128);
+);
+
+lorawan_key!(
+    /// The [`JSIntKey`] is the LoRaWAN 1.1 join server integrity key, used to compute the MIC of
+    /// JoinAccepts answering a rejoin request.
+    pub struct JSIntKey(AES128);
+);
+
+lorawan_key!(
+    /// The [`JSEncKey`] is the LoRaWAN 1.1 join server encryption key, used to encrypt
+    /// JoinAccepts answering a rejoin request.
+    pub struct JSEncKey(AES128);
+);
+
+/// Derives a LoRaWAN 1.1 key by encrypting `prefix | data`, padded to 16 bytes, with `key`.
+fn derive_key_1_1<F: CryptoFactory>(
+    factory: &F,
+    key: &AES128,
+    prefix: u8,
+    data: &[&[u8]],
+) -> AES128 {
+    let mut block = [0u8; 16];
+    block[0] = prefix;
+    let mut pos = 1;
+    for part in data {
+        block[pos..pos + part.len()].copy_from_slice(part);
+        pos += part.len();
+    }
+    factory.new_enc(key).encrypt_block(&mut block);
+    AES128(block)
+}
+
+impl NwkKey {
+    /// Derives the LoRaWAN 1.1 network session keys of an OTAA session from the JoinNonce of the
+    /// JoinAccept, the JoinEUI and the DevNonce of the JoinRequest.
+    ///
+    /// Returns the `FNwkSIntKey`, `SNwkSIntKey` and `NwkSEncKey`, in that order.
+    pub fn derive_nwk_s_keys<F: CryptoFactory, T: AsRef<[u8]>, TT: AsRef<[u8]>>(
+        &self,
+        factory: &F,
+        join_nonce: &AppNonce<T>,
+        join_eui: &AppEui,
+        dev_nonce: &DevNonce<TT>,
+    ) -> (FNwkSIntKey, SNwkSIntKey, NwkSEncKey) {
+        let data = [join_nonce.as_ref(), join_eui.as_ref(), dev_nonce.as_ref()];
+        let derive = |prefix| derive_key_1_1(factory, &self.0, prefix, &data);
+        (FNwkSIntKey(derive(0x01)), SNwkSIntKey(derive(0x03)), NwkSEncKey(derive(0x04)))
+    }
+
+    /// Derives the LoRaWAN 1.1 join server keys of the end-device with `dev_eui`.
+    ///
+    /// Returns the `JSIntKey` and `JSEncKey`, in that order.
+    pub fn derive_js_keys<F: CryptoFactory>(
+        &self,
+        factory: &F,
+        dev_eui: &DevEui,
+    ) -> (JSIntKey, JSEncKey) {
+        let derive = |prefix| derive_key_1_1(factory, &self.0, prefix, &[dev_eui.as_ref()]);
+        (JSIntKey(derive(0x06)), JSEncKey(derive(0x05)))
+    }
+}
+
+impl AppKey {
+    /// Derives the LoRaWAN 1.1 application session key of an OTAA session from the JoinNonce of
+    /// the JoinAccept, the JoinEUI and the DevNonce of the JoinRequest.
+    ///
+    /// With LoRaWAN 1.0.x, use
+    /// [`DecryptedJoinAcceptPayload::derive_appskey`](crate::parser::DecryptedJoinAcceptPayload::derive_appskey)
+    /// instead.
+    pub fn derive_app_s_key<F: CryptoFactory, T: AsRef<[u8]>, TT: AsRef<[u8]>>(
+        &self,
+        factory: &F,
+        join_nonce: &AppNonce<T>,
+        join_eui: &AppEui,
+        dev_nonce: &DevNonce<TT>,
+    ) -> AppSKey {
+        let data = [join_nonce.as_ref(), join_eui.as_ref(), dev_nonce.as_ref()];
+        AppSKey(derive_key_1_1(factory, &self.0, 0x02, &data))
+    }
+}
+
+macro_rules! lorawan_eui {
+    (
+        $(#[$outer:meta])*
+        pub struct $type:ident(EUI64<[u8; 8]>);
+    ) => {
+        $(#[$outer])*
+        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        #[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+        pub struct $type(EUI64<[u8; 8]>);
+
+        #[cfg(feature = "serde")]
+        impl_serde_hex!($type(EUI64<[u8; 8]>));
+
+        impl From<[u8;8]> for $type {
+            fn from(key: [u8; 8]) -> Self {
+                $type(EUI64::from(key))
+            }
+        }
+
+        impl From<$type> for EUI64<[u8; 8]> {
+            fn from(key: $type) -> Self {
+                key.0
+            }
+        }
+
+        impl TryFrom<&[u8]> for $type {
+            type Error = LengthError;
+
+            /// Creates an EUI from a slice, which must be exactly 8 bytes long.
+            fn try_from(v: &[u8]) -> Result<Self, Self::Error> {
+                <[u8; 8]>::try_from(v).map(Self::from).map_err(|_| LengthError)
+            }
+        }
+
+        #[cfg(feature = "rand")]
+        #[cfg_attr(docsrs, doc(cfg(feature = "rand")))]
+        impl $type {
+            /// Creates a random EUI, eg: for test devices without an assigned one.
+            pub fn generate<R: rand_core::RngCore>(rng: &mut R) -> Self {
+                let mut eui = [0u8; 8];
+                rng.fill_bytes(&mut eui);
+                Self::from(eui)
+            }
+        }
+
+        impl AsRef<[u8]> for $type {
+            fn as_ref(&self) -> &[u8] {
+                &self.0.as_ref()
+            }
+        }
+    };
+}
+
+lorawan_eui!(
+    /// [`DevEui`] is a global end-device ID in the IEEE EUI64 address space
+    /// that uniquely identifies the end-device across roaming networks.
+    ///
+    /// All end-devices SHALL have an assigned `DevEui` regardless of which
+    /// activation procedure is used (i.e., ABP or OTAA).
+    ///
+    /// It is a recommended practice that `DevEui` should also be available on
+    /// an end-device label for the purpose of end-device administration.
+    ///
+    /// To create from a hex-encoded LSB string:
+    /// ```
+    /// use core::str::FromStr;
+    /// use lorawan::keys::DevEui;
+    /// let dev_eui = DevEui::from_str("0011223344556677").unwrap();
+    /// ```
+    ///
+    /// To create from a byte array, you should enter the bytes in LSB format:
+    /// ```
+    /// use lorawan::keys::DevEui;
+    /// let dev_eui = DevEui::from([0x77, 0x66, 0x55, 0x44, 0x33, 0x22, 0x11, 0x00]);
+    /// ```
+    pub struct DevEui(EUI64<[u8; 8]>);
+);
+lorawan_eui!(
+    /// The [`AppEui`] is a global application ID in IEEE EUI64 address space
+    /// that uniquely identifies the entity able to process the JoinReq frame.
+    ///
+    /// For OTAA end-devices, `AppEui` SHALL be stored in the end-device before
+    /// the Join procedure is executed, although some network servers ignore
+    /// this value.
+    /// `AppEui` is not required for ABP-only end-devices.
+    ///
+    /// As of LoRaWAN 1.0.4, `AppEui` is called `JoinEui`.
+    ///
+    /// To create from a hex-encoded LSB string:
+    /// ```
+    /// use core::str::FromStr;
+    /// use lorawan::keys::AppEui;
+    /// let app_eui = AppEui::from_str("0011223344556677").unwrap();
+    /// ```
+    ///
+    /// To create from a byte array, you should enter the bytes in LSB format:
+    /// ```
+    /// use lorawan::keys::AppEui;
+    /// let app_eui = AppEui::from([0x77, 0x66, 0x55, 0x44, 0x33, 0x22, 0x11, 0x00]);
+    /// ```
+    pub struct AppEui(EUI64<[u8; 8]>);
+);
+
+/// [`AES128`] represents 128-bit AES key.
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+#[derive(Debug, Default, Eq, Clone, Copy)]
+pub struct AES128(pub [u8; 16]);
+
+#[cfg(feature = "serde")]
+impl_serde_hex!(AES128([u8; 16]));
+
+impl PartialEq for AES128 {
+    /// Compares the keys in constant time.
+    fn eq(&self, other: &Self) -> bool {
+        self.0[..].ct_eq(&other.0[..]).into()
+    }
+}
+
+impl From<[u8; 16]> for AES128 {
+    fn from(v: [u8; 16]) -> Self {
+        AES128(v)
+    }
+}
+
+impl From<AES128> for [u8; 16] {
+    fn from(v: AES128) -> Self {
+        v.0
+    }
+}
+
+impl AsRef<[u8]> for AES128 {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl AES128 {
+    /// Gives a reference to the raw key bytes.
+    pub fn as_bytes(&self) -> &[u8; 16] {
+        &self.0
+    }
+
+    /// Converts the key into its raw bytes.
+    pub fn into_bytes(self) -> [u8; 16] {
+        self.0
+    }
+}
+
+/// [`MIC`] represents LoRaWAN message integrity code (MIC).
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+#[derive(Debug, Default, Eq, Clone, Copy)]
+pub struct MIC(pub [u8; 4]);
+
+impl PartialEq for MIC {
+    /// Compares the MICs in constant time, as a received MIC is checked against a computed one.
+    fn eq(&self, other: &Self) -> bool {
+        self.0[..].ct_eq(&other.0[..]).into()
+    }
+}
+
+impl From<[u8; 4]> for MIC {
+    fn from(v: [u8; 4]) -> Self {
+        MIC(v)
+    }
+}
+
+impl AsRef<[u8]> for MIC {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl TryFrom<&[u8]> for MIC {
+    type Error = LengthError;
+
+    /// Creates a MIC from a slice, which must be exactly 4 bytes long.
+    fn try_from(v: &[u8]) -> Result<Self, Self::Error> {
+        <[u8; 4]>::try_from(v).map(MIC).map_err(|_| LengthError)
+    }
+}
+
+/// Error returned when a slice has the wrong length for the type being created from it.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub struct LengthError;
+
+impl core::fmt::Display for LengthError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str("invalid length")
+    }
+}
+
+/// Trait for implementations of AES128 encryption.
+pub trait Encrypter {
+    fn encrypt_block(&self, block: &mut [u8]);
+}
+
+/// Trait for implementations of AES128 decryption.
+pub trait Decrypter {
+    fn decrypt_block(&self, block: &mut [u8]);
+}
+
+/// Trait for implementations of CMAC (RFC4493).
+pub trait Mac {
+    fn input(&mut self, data: &[u8]);
+    fn reset(&mut self);
+    fn result(self) -> [u8; 16];
+}
+
+/// Represents an abstraction over the crypto functions.
+///
+/// This trait provides a way to pick a different implementation of the crypto primitives.
+pub trait CryptoFactory {
+    type E: Encrypter;
+    type D: Decrypter;
+    type M: Mac;
+
+    /// Method that creates an Encrypter.
+    fn new_enc(&self, key: &AES128) -> Self::E;
+
+    /// Method that creates a Decrypter.
+    fn new_dec(&self, key: &AES128) -> Self::D;
+
+    /// Method that creates a MAC calculator.
+    fn new_mac(&self, key: &AES128) -> Self::M;
+}
+
+impl<T: CryptoFactory + ?Sized> CryptoFactory for &T {
+    type E = T::E;
+    type D = T::D;
+    type M = T::M;
+
+    fn new_enc(&self, key: &AES128) -> Self::E {
+        (**self).new_enc(key)
+    }
+
+    fn new_dec(&self, key: &AES128) -> Self::D {
+        (**self).new_dec(key)
+    }
+
+    fn new_mac(&self, key: &AES128) -> Self::M {
+        (**self).new_mac(key)
+    }
+}
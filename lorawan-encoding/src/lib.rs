@@ -11,6 +11,7 @@ pub mod maccommands;
 pub mod multicast;
 pub mod packet_length;
 pub mod parser;
+pub mod relay;
 pub mod string;
 pub mod types;
 
@@ -21,4 +22,8 @@ pub mod extra;
 #[cfg_attr(docsrs, doc(cfg(feature = "default-crypto")))]
 pub mod default_crypto;
 
+#[cfg(feature = "test-util")]
+#[cfg_attr(docsrs, doc(cfg(feature = "test-util")))]
+pub mod test_util;
+
 mod securityhelpers;
@@ -1,4 +1,5 @@
 use super::maccommands::{mac_commands_len, SerializableMacCommand};
+use crate::packet_length::phy::mac::fhdr::FOPTS_MAX_LEN;
 use crate::types::{ChannelMask, DLSettings, DataRateRange, Frequency, Redundancy};
 
 #[derive(Debug, PartialEq)]
@@ -11,6 +12,23 @@ pub enum Error {
     MaxEirpOutOfRange,
     NanoSecondsOutOfRange,
     BufferTooShort,
+    MacCommandsTooBigForFOpts,
+}
+
+impl core::fmt::Display for Error {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let msg = match self {
+            Error::InvalidDataRate => "invalid data rate",
+            Error::InvalidTxPower => "invalid TX power",
+            Error::MarginOutOfRange => "margin out of range",
+            Error::DelayOutOfRange => "delay out of range",
+            Error::MaxEirpOutOfRange => "max EIRP out of range",
+            Error::NanoSecondsOutOfRange => "nanoseconds out of range",
+            Error::BufferTooShort => "buffer too short",
+            Error::MacCommandsTooBigForFOpts => "MAC commands too big for FOpts",
+        };
+        f.write_str(msg)
+    }
 }
 
 /// LinkCheckReqCreator serves for creating LinkCheckReq MacCommand.
@@ -609,6 +627,21 @@ pub fn build_mac_commands<T: AsMut<[u8]>>(
     Ok(i)
 }
 
+/// Serializes MAC commands, eg: the answers to the commands of a downlink, to be piggybacked in
+/// the FOpts of a frame and returns the number of bytes written.
+///
+/// Fails when the commands do not fit in the 15 bytes of the FOpts, in which case they have to be
+/// sent as the FRMPayload of a frame with FPort 0.
+pub fn build_f_opts<T: AsMut<[u8]>>(
+    cmds: &[&dyn SerializableMacCommand],
+    out: T,
+) -> Result<usize, Error> {
+    if mac_commands_len(cmds) > FOPTS_MAX_LEN {
+        return Err(Error::MacCommandsTooBigForFOpts);
+    }
+    build_mac_commands(cmds, out)
+}
+
 #[doc(inline)]
 pub use crate::maccommands::DownlinkMacCommandCreator;
 
@@ -34,6 +34,18 @@ pub enum Error {
     InvalidDataRateRange,
 }
 
+impl core::fmt::Display for Error {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let msg = match self {
+            Error::UnknownMacCommand => "unknown MAC command",
+            Error::BufferTooShort => "buffer too short",
+            Error::InvalidIndex => "invalid index",
+            Error::InvalidDataRateRange => "invalid data rate range",
+        };
+        f.write_str(msg)
+    }
+}
+
 pub trait SerializableMacCommand {
     fn payload_bytes(&self) -> &[u8];
     fn cid(&self) -> u8;
@@ -48,6 +60,7 @@ pub fn mac_commands_len(cmds: &[&dyn SerializableMacCommand]) -> usize {
 #[derive(Debug, PartialEq, CommandHandler)]
 #[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
 /// Downlink MAC commands, transmitted by Network Server
+#[cmd(cid_type = DownlinkCid)]
 pub enum DownlinkMacCommand<'a> {
     // LoRaWAN 1.0.0+ commands
     /// LinkCheckAns payload handling (LoRaWAN 1.0.0+)
@@ -96,6 +109,7 @@ pub enum DownlinkMacCommand<'a> {
 #[derive(Debug, PartialEq, CommandHandler)]
 #[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
 /// Uplink MAC commands, transmitted by End-device
+#[cmd(cid_type = UplinkCid)]
 pub enum UplinkMacCommand<'a> {
     // LoRaWAN 1.0.0 commands
     /// LinkCheckReq payload handling (LoRaWAN 1.0.0+)
@@ -141,6 +155,137 @@ pub enum UplinkMacCommand<'a> {
     DeviceTimeReq(DeviceTimeReqPayload),
 }
 
+/// Direction a MAC command is transmitted in. CIDs are only unique within a direction.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub enum Direction {
+    /// From the end-device to the Network Server.
+    Uplink,
+    /// From the Network Server to the end-device.
+    Downlink,
+}
+
+/// Typed MAC command identifier, namespaced by [`Direction`].
+///
+/// # Examples
+///
+/// ```
+/// use lorawan::maccommands::{Cid, Direction, DownlinkCid, UplinkCid};
+/// assert_eq!(Cid::try_from((Direction::Uplink, 0x02)), Ok(Cid::Uplink(UplinkCid::LinkCheckReq)));
+/// assert_eq!(Cid::try_from((Direction::Downlink, 0x02)), Ok(Cid::Downlink(DownlinkCid::LinkCheckAns)));
+/// assert_eq!(u8::from(Cid::Downlink(DownlinkCid::LinkADRReq)), 0x03);
+/// ```
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub enum Cid {
+    Uplink(UplinkCid),
+    Downlink(DownlinkCid),
+}
+
+impl Cid {
+    /// Direction the command is transmitted in.
+    pub fn direction(&self) -> Direction {
+        match self {
+            Cid::Uplink(_) => Direction::Uplink,
+            Cid::Downlink(_) => Direction::Downlink,
+        }
+    }
+}
+
+impl TryFrom<(Direction, u8)> for Cid {
+    type Error = Error;
+
+    fn try_from((direction, cid): (Direction, u8)) -> Result<Self, Self::Error> {
+        match direction {
+            Direction::Uplink => Ok(Cid::Uplink(cid.try_into()?)),
+            Direction::Downlink => Ok(Cid::Downlink(cid.try_into()?)),
+        }
+    }
+}
+
+impl From<Cid> for u8 {
+    fn from(cid: Cid) -> u8 {
+        match cid {
+            Cid::Uplink(c) => c as u8,
+            Cid::Downlink(c) => c as u8,
+        }
+    }
+}
+
+/// Identifiers of the MAC commands transmitted by the end-device.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+#[repr(u8)]
+pub enum UplinkCid {
+    LinkCheckReq = LinkCheckReqPayload::cid(),
+    LinkADRAns = LinkADRAnsPayload::cid(),
+    DutyCycleAns = DutyCycleAnsPayload::cid(),
+    RXParamSetupAns = RXParamSetupAnsPayload::cid(),
+    DevStatusAns = DevStatusAnsPayload::cid(),
+    NewChannelAns = NewChannelAnsPayload::cid(),
+    RXTimingSetupAns = RXTimingSetupAnsPayload::cid(),
+    TXParamSetupAns = TXParamSetupAnsPayload::cid(),
+    DlChannelAns = DlChannelAnsPayload::cid(),
+    DeviceTimeReq = DeviceTimeReqPayload::cid(),
+}
+
+impl TryFrom<u8> for UplinkCid {
+    type Error = Error;
+
+    fn try_from(cid: u8) -> Result<Self, Self::Error> {
+        match cid {
+            c if c == Self::LinkCheckReq as u8 => Ok(Self::LinkCheckReq),
+            c if c == Self::LinkADRAns as u8 => Ok(Self::LinkADRAns),
+            c if c == Self::DutyCycleAns as u8 => Ok(Self::DutyCycleAns),
+            c if c == Self::RXParamSetupAns as u8 => Ok(Self::RXParamSetupAns),
+            c if c == Self::DevStatusAns as u8 => Ok(Self::DevStatusAns),
+            c if c == Self::NewChannelAns as u8 => Ok(Self::NewChannelAns),
+            c if c == Self::RXTimingSetupAns as u8 => Ok(Self::RXTimingSetupAns),
+            c if c == Self::TXParamSetupAns as u8 => Ok(Self::TXParamSetupAns),
+            c if c == Self::DlChannelAns as u8 => Ok(Self::DlChannelAns),
+            c if c == Self::DeviceTimeReq as u8 => Ok(Self::DeviceTimeReq),
+            _ => Err(Error::UnknownMacCommand),
+        }
+    }
+}
+
+/// Identifiers of the MAC commands transmitted by the Network Server.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+#[repr(u8)]
+pub enum DownlinkCid {
+    LinkCheckAns = LinkCheckAnsPayload::cid(),
+    LinkADRReq = LinkADRReqPayload::cid(),
+    DutyCycleReq = DutyCycleReqPayload::cid(),
+    RXParamSetupReq = RXParamSetupReqPayload::cid(),
+    DevStatusReq = DevStatusReqPayload::cid(),
+    NewChannelReq = NewChannelReqPayload::cid(),
+    RXTimingSetupReq = RXTimingSetupReqPayload::cid(),
+    TXParamSetupReq = TXParamSetupReqPayload::cid(),
+    DlChannelReq = DlChannelReqPayload::cid(),
+    DeviceTimeAns = DeviceTimeAnsPayload::cid(),
+}
+
+impl TryFrom<u8> for DownlinkCid {
+    type Error = Error;
+
+    fn try_from(cid: u8) -> Result<Self, Self::Error> {
+        match cid {
+            c if c == Self::LinkCheckAns as u8 => Ok(Self::LinkCheckAns),
+            c if c == Self::LinkADRReq as u8 => Ok(Self::LinkADRReq),
+            c if c == Self::DutyCycleReq as u8 => Ok(Self::DutyCycleReq),
+            c if c == Self::RXParamSetupReq as u8 => Ok(Self::RXParamSetupReq),
+            c if c == Self::DevStatusReq as u8 => Ok(Self::DevStatusReq),
+            c if c == Self::NewChannelReq as u8 => Ok(Self::NewChannelReq),
+            c if c == Self::RXTimingSetupReq as u8 => Ok(Self::RXTimingSetupReq),
+            c if c == Self::TXParamSetupReq as u8 => Ok(Self::TXParamSetupReq),
+            c if c == Self::DlChannelReq as u8 => Ok(Self::DlChannelReq),
+            c if c == Self::DeviceTimeAns as u8 => Ok(Self::DeviceTimeAns),
+            _ => Err(Error::UnknownMacCommand),
+        }
+    }
+}
+
 macro_rules! create_ack_fn {
     (
         $(#[$outer:meta])*
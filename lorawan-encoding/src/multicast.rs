@@ -1,7 +1,12 @@
+use crate::maccommandcreator;
 use crate::maccommands::{Error, MacCommandIterator, SerializableMacCommand};
+use crate::parser::DevAddr;
+use crate::types::Frequency;
 use lorawan_macros::CommandHandler;
 
 const MAX_GROUPS: usize = 4;
+/// Duration of a beacon period in seconds, the time unit of Class B session timeouts.
+const BEACON_PERIOD_S: u32 = 128;
 
 #[derive(Debug, PartialEq, CommandHandler)]
 #[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
@@ -57,13 +62,14 @@ impl<'a> McGroupStatusAnsPayload<'a> {
         // |  RFU  | NbTotalGroups | AnsGroupMask |
         // | 1 bit |    3 bits     |    4 bits    |
         // Table 5: McGroupStatusAns
-        let nb_total_groups = (status >> 4) & 0x07;
-        nb_total_groups as usize * Self::ITEM_LEN
+        // The status is followed by the McGroupID and McAddr of each group in AnsGroupMask
+        let nb_ans_groups = (status & 0x0f).count_ones();
+        1 + nb_ans_groups as usize * Self::ITEM_LEN
     }
 
     /// Maximum possible length of the payload
     pub const fn max_len() -> usize {
-        MAX_GROUPS * Self::ITEM_LEN
+        1 + MAX_GROUPS * Self::ITEM_LEN
     }
 
     /// Actual length of this specific payload
@@ -71,4 +77,358 @@ impl<'a> McGroupStatusAnsPayload<'a> {
     pub fn len(&self) -> usize {
         Self::required_len(self.0[0])
     }
+
+    /// Number of multicast groups set up on the device.
+    pub fn nb_total_groups(&self) -> u8 {
+        (self.0[0] >> 4) & 0x07
+    }
+
+    /// Mask of the groups reported in the answer, ie: the requested groups which are set up.
+    pub fn ans_group_mask(&self) -> u8 {
+        self.0[0] & 0x0f
+    }
+
+    /// Gives the McGroupID and McAddr of each reported group.
+    pub fn groups(&self) -> impl Iterator<Item = (u8, DevAddr<[u8; 4]>)> + 'a {
+        let data = &self.0[..self.len()];
+        (1..data.len()).step_by(Self::ITEM_LEN).map(move |i| {
+            (data[i] & 0x03, DevAddr::from([data[i + 1], data[i + 2], data[i + 3], data[i + 4]]))
+        })
+    }
+}
+
+/// Time window of a multicast session, in seconds since the GPS epoch modulo 2^32.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub struct SessionWindow {
+    /// Time at which the session starts, ie: the receive window opens.
+    pub open: u32,
+    /// Time at which the session times out, ie: the receive window closes.
+    pub close: u32,
+}
+
+impl SessionWindow {
+    /// Gives the number of seconds from `now` until the session starts, as reported in the
+    /// TimeToStart field of the answer, or None if the session has already started.
+    ///
+    /// # Argument
+    ///
+    /// * now - the current time in seconds since the GPS epoch modulo 2^32.
+    pub fn time_to_start(&self, now: u32) -> Option<u32> {
+        let delta = self.open.wrapping_sub(now);
+        // The times wrap around, so anything more than half the range away is in the past
+        if delta > i32::MAX as u32 {
+            None
+        } else {
+            Some(delta)
+        }
+    }
+}
+
+fn session_time(bytes: &[u8]) -> u32 {
+    u32::from_le_bytes([bytes[1], bytes[2], bytes[3], bytes[4]])
+}
+
+fn time_to_start(bytes: &[u8]) -> Option<u32> {
+    // |  RFU   | McGroupUndefined | FreqError | DRError | McGroupID |
+    // | 3 bits |      1 bit       |   1 bit   |  1 bit  |  2 bits   |
+    if bytes[0] & 0x1c != 0 {
+        return None;
+    }
+    Some(u32::from_le_bytes([bytes[1], bytes[2], bytes[3], 0]))
 }
+
+impl McClassCSessionReqPayload<'_> {
+    /// The multicast group the session is set up for.
+    pub fn mc_group_id(&self) -> u8 {
+        self.0[0] & 0x03
+    }
+
+    /// Start of the session in seconds since the GPS epoch modulo 2^32.
+    pub fn session_time(&self) -> u32 {
+        session_time(self.0)
+    }
+
+    /// The session lasts at most 2^SessionTimeOut seconds.
+    pub fn session_time_out(&self) -> u8 {
+        self.0[5] & 0x0f
+    }
+
+    /// The frequency of the multicast downlinks.
+    pub fn dl_frequency(&self) -> Frequency<'_> {
+        Frequency::new_from_raw(&self.0[6..9])
+    }
+
+    /// The data rate of the multicast downlinks.
+    pub fn data_rate(&self) -> u8 {
+        self.0[9]
+    }
+
+    /// Gives the times at which the Class C session starts and times out.
+    pub fn session_window(&self) -> SessionWindow {
+        let open = self.session_time();
+        SessionWindow { open, close: open.wrapping_add(1 << self.session_time_out()) }
+    }
+}
+
+impl McClassBSessionReqPayload<'_> {
+    /// The multicast group the session is set up for.
+    pub fn mc_group_id(&self) -> u8 {
+        self.0[0] & 0x03
+    }
+
+    /// Start of the session in seconds since the GPS epoch modulo 2^32, which is the start of a
+    /// beacon period.
+    pub fn session_time(&self) -> u32 {
+        session_time(self.0)
+    }
+
+    /// The session lasts at most 2^SessionTimeOut beacon periods.
+    pub fn session_time_out(&self) -> u8 {
+        self.0[5] & 0x0f
+    }
+
+    /// The periodicity of the multicast ping slots, see [`ping_period`](Self::ping_period).
+    pub fn periodicity(&self) -> u8 {
+        (self.0[5] >> 4) & 0x07
+    }
+
+    /// The number of 30 ms slots between two multicast ping slots, ie: 2^(5 + Periodicity),
+    /// which is about 2^Periodicity seconds.
+    pub fn ping_period(&self) -> u32 {
+        1 << (5 + self.periodicity())
+    }
+
+    /// The frequency of the multicast downlinks.
+    pub fn dl_frequency(&self) -> Frequency<'_> {
+        Frequency::new_from_raw(&self.0[6..9])
+    }
+
+    /// The data rate of the multicast downlinks.
+    pub fn data_rate(&self) -> u8 {
+        self.0[9]
+    }
+
+    /// Gives the times at which the Class B session starts and times out.
+    pub fn session_window(&self) -> SessionWindow {
+        let open = self.session_time();
+        let duration = BEACON_PERIOD_S.saturating_mul(1 << self.session_time_out());
+        SessionWindow { open, close: open.wrapping_add(duration) }
+    }
+}
+
+impl McClassCSessionAnsPayload<'_> {
+    /// The multicast group the answer is for.
+    pub fn mc_group_id(&self) -> u8 {
+        self.0[0] & 0x03
+    }
+
+    /// Seconds until the session starts, or None if the session setup failed.
+    pub fn time_to_start(&self) -> Option<u32> {
+        time_to_start(self.0)
+    }
+}
+
+impl McClassBSessionAnsPayload<'_> {
+    /// The multicast group the answer is for.
+    pub fn mc_group_id(&self) -> u8 {
+        self.0[0] & 0x03
+    }
+
+    /// Seconds until the session starts, or None if the session setup failed.
+    pub fn time_to_start(&self) -> Option<u32> {
+        time_to_start(self.0)
+    }
+}
+
+impl McGroupSetupAnsPayload<'_> {
+    /// The multicast group the answer is for.
+    pub fn mc_group_id(&self) -> u8 {
+        self.0[0] & 0x03
+    }
+
+    /// Whether the device rejected the setup because it does not support the multicast group.
+    pub fn id_error(&self) -> bool {
+        self.0[0] & 0x04 != 0
+    }
+}
+
+impl McGroupStatusReqPayload<'_> {
+    /// Mask of the multicast groups whose status is requested.
+    pub fn req_group_mask(&self) -> u8 {
+        self.0[0] & 0x0f
+    }
+}
+
+impl McGroupDeleteReqPayload<'_> {
+    /// The multicast group to delete.
+    pub fn mc_group_id(&self) -> u8 {
+        self.0[0] & 0x03
+    }
+}
+
+impl McGroupDeleteAnsPayload<'_> {
+    /// The multicast group the answer is for.
+    pub fn mc_group_id(&self) -> u8 {
+        self.0[0] & 0x03
+    }
+
+    /// Whether the multicast group was not set up, and thus could not be deleted.
+    pub fn mc_group_undefined(&self) -> bool {
+        self.0[0] & 0x04 != 0
+    }
+}
+
+impl McGroupSetupAnsCreator {
+    /// Sets the multicast group the McGroupSetupAns answers for.
+    ///
+    /// # Argument
+    ///
+    /// * mc_group_id - the McGroupID of the McGroupSetupReq, between 0 and 3.
+    pub fn set_mc_group_id(&mut self, mc_group_id: u8) -> &mut Self {
+        self.data[1] &= !0x03;
+        self.data[1] |= mc_group_id & 0x03;
+
+        self
+    }
+
+    /// Sets the IDerror bit of the McGroupSetupAns.
+    ///
+    /// # Argument
+    ///
+    /// * id_error - true when the multicast group is not supported by the device.
+    pub fn set_id_error(&mut self, id_error: bool) -> &mut Self {
+        self.data[1] &= !0x04;
+        self.data[1] |= (id_error as u8) << 2;
+
+        self
+    }
+}
+
+impl McGroupStatusAnsCreator {
+    /// Sets the number of multicast groups set up on the device.
+    ///
+    /// # Argument
+    ///
+    /// * nb_total_groups - the number of groups, between 0 and 4.
+    pub fn set_nb_total_groups(&mut self, nb_total_groups: u8) -> &mut Self {
+        self.data[1] &= !0x70;
+        self.data[1] |= (nb_total_groups & 0x07) << 4;
+
+        self
+    }
+
+    /// Reports a multicast group in the McGroupStatusAns, which should only be done for groups
+    /// which were requested and are set up. Groups are kept in increasing McGroupID order, and
+    /// reporting a group again replaces its McAddr.
+    ///
+    /// # Argument
+    ///
+    /// * mc_group_id - the McGroupID of the group, between 0 and 3.
+    /// * mc_addr - the multicast address of the group.
+    pub fn add_group(&mut self, mc_group_id: u8, mc_addr: DevAddr<[u8; 4]>) -> &mut Self {
+        const ITEM_LEN: usize = McGroupStatusAnsPayload::ITEM_LEN;
+        let mc_group_id = mc_group_id & 0x03;
+        let mask = self.data[1] & 0x0f;
+        let pos = 2 + (mask & ((1 << mc_group_id) - 1)).count_ones() as usize * ITEM_LEN;
+        if mask & (1 << mc_group_id) == 0 {
+            // make room for the group, keeping the following ones
+            let end = 2 + mask.count_ones() as usize * ITEM_LEN;
+            self.data.copy_within(pos..end, pos + ITEM_LEN);
+            self.data[1] |= 1 << mc_group_id;
+        }
+        self.data[pos] = mc_group_id;
+        self.data[pos + 1..pos + ITEM_LEN].copy_from_slice(mc_addr.as_ref());
+
+        self
+    }
+}
+
+impl McGroupDeleteAnsCreator {
+    /// Sets the multicast group the McGroupDeleteAns answers for.
+    ///
+    /// # Argument
+    ///
+    /// * mc_group_id - the McGroupID of the McGroupDeleteReq, between 0 and 3.
+    pub fn set_mc_group_id(&mut self, mc_group_id: u8) -> &mut Self {
+        self.data[1] &= !0x03;
+        self.data[1] |= mc_group_id & 0x03;
+
+        self
+    }
+
+    /// Sets the McGroupUndefined bit of the McGroupDeleteAns.
+    ///
+    /// # Argument
+    ///
+    /// * undefined - true when the multicast group to delete was not set up.
+    pub fn set_mc_group_undefined(&mut self, undefined: bool) -> &mut Self {
+        self.data[1] &= !0x04;
+        self.data[1] |= (undefined as u8) << 2;
+
+        self
+    }
+}
+
+macro_rules! impl_session_ans_creator {
+    ($creator:ident) => {
+        impl $creator {
+            /// Sets the multicast group the answer is for.
+            ///
+            /// # Argument
+            ///
+            /// * mc_group_id - the McGroupID of the session request, between 0 and 3.
+            pub fn set_mc_group_id(&mut self, mc_group_id: u8) -> &mut Self {
+                self.data[1] &= !0x03;
+                self.data[1] |= mc_group_id & 0x03;
+
+                self
+            }
+
+            /// Sets the DRError bit, when the data rate of the session is not supported.
+            pub fn set_dr_error(&mut self, error: bool) -> &mut Self {
+                self.data[1] &= !0x04;
+                self.data[1] |= (error as u8) << 2;
+
+                self
+            }
+
+            /// Sets the FreqError bit, when the frequency of the session is not supported.
+            pub fn set_freq_error(&mut self, error: bool) -> &mut Self {
+                self.data[1] &= !0x08;
+                self.data[1] |= (error as u8) << 3;
+
+                self
+            }
+
+            /// Sets the McGroupUndefined bit, when the multicast group has not been set up.
+            pub fn set_mc_group_undefined(&mut self, error: bool) -> &mut Self {
+                self.data[1] &= !0x10;
+                self.data[1] |= (error as u8) << 4;
+
+                self
+            }
+
+            /// Sets the TimeToStart of the answer, which is only meaningful if no error bit is set.
+            ///
+            /// # Argument
+            ///
+            /// * time_to_start - the seconds until the session starts, which must fit in 24 bits, eg:
+            ///   from [`SessionWindow::time_to_start`].
+            pub fn set_time_to_start(
+                &mut self,
+                time_to_start: u32,
+            ) -> Result<&mut Self, maccommandcreator::Error> {
+                if time_to_start > 0xff_ffff {
+                    return Err(maccommandcreator::Error::DelayOutOfRange);
+                }
+                self.data[2..5].copy_from_slice(&time_to_start.to_le_bytes()[..3]);
+
+                Ok(self)
+            }
+        }
+    };
+}
+
+impl_session_ans_creator!(McClassCSessionAnsCreator);
+impl_session_ans_creator!(McClassBSessionAnsCreator);
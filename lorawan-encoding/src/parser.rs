@@ -21,7 +21,10 @@
 //! }
 //! ```
 
-use super::keys::{AppKey, AppSKey, CryptoFactory, Encrypter, NwkSKey, AES128, MIC};
+use super::creator::{join_accept_mic, JoinAcceptMicVersion};
+use super::keys::{
+    AppEui, AppKey, AppSKey, CryptoFactory, Encrypter, JSIntKey, NwkSKey, AES128, MIC,
+};
 use crate::types::{ChannelMask, DLSettings, Frequency};
 
 use super::securityhelpers;
@@ -40,6 +43,50 @@ pub enum Error {
     InvalidMessageType,
     InvalidPayload,
     UnsupportedMajorVersion,
+    FCntGapTooLarge,
+}
+
+impl core::fmt::Display for Error {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let msg = match self {
+            Error::InvalidData => "invalid data",
+            Error::InvalidMic => "invalid MIC",
+            Error::InvalidKey => "invalid key",
+            Error::InvalidMessageType => "invalid message type",
+            Error::InvalidPayload => "invalid payload",
+            Error::UnsupportedMajorVersion => "unsupported major version",
+            Error::FCntGapTooLarge => "frame counter gap too large",
+        };
+        f.write_str(msg)
+    }
+}
+
+/// MIC check failure, giving both the received and the computed MIC.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub struct MicMismatch {
+    /// MIC carried by the frame.
+    pub expected: MIC,
+    /// MIC computed with the given key.
+    pub computed: MIC,
+}
+
+impl core::fmt::Display for MicMismatch {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "invalid MIC: expected {:02x?}, computed {:02x?}",
+            self.expected.0, self.computed.0
+        )
+    }
+}
+
+fn check_mic(expected: MIC, computed: MIC) -> Result<(), MicMismatch> {
+    if expected == computed {
+        Ok(())
+    } else {
+        Err(MicMismatch { expected, computed })
+    }
 }
 
 macro_rules! fixed_len_struct {
@@ -296,6 +343,19 @@ impl<T: AsRef<[u8]>, F: CryptoFactory> JoinRequestPayload<T, F> {
         EUI64::new_from_raw(&self.0.as_ref()[1..9])
     }
 
+    /// Gives the JoinEUI (called AppEUI before LoRaWAN 1.0.4) of the JoinRequest.
+    pub fn join_eui(&self) -> AppEui {
+        let mut eui = [0u8; 8];
+        eui.copy_from_slice(&self.0.as_ref()[1..9]);
+        AppEui::from(eui)
+    }
+
+    /// Whether the JoinEUI of the JoinRequest is one of `allowed`, eg: for a network server that
+    /// filters joins by JoinEUI.
+    pub fn join_eui_allowed(&self, allowed: &[AppEui]) -> bool {
+        allowed.contains(&self.join_eui())
+    }
+
     /// Gives the DEV EUI of the JoinRequest.
     pub fn dev_eui(&self) -> EUI64<&[u8]> {
         EUI64::new_from_raw(&self.0.as_ref()[9..17])
@@ -311,6 +371,11 @@ impl<T: AsRef<[u8]>, F: CryptoFactory> JoinRequestPayload<T, F> {
         self.mic() == self.calculate_mic(key)
     }
 
+    /// Verifies that the JoinRequest has correct MIC, giving both MICs if it does not.
+    pub fn validate_mic_verbose(&self, key: &AES128) -> Result<(), MicMismatch> {
+        check_mic(self.mic(), self.calculate_mic(key))
+    }
+
     fn calculate_mic(&self, key: &AES128) -> MIC {
         let d = self.0.as_ref();
         securityhelpers::calculate_mic(&d[..d.len() - MIC_LEN], self.1.new_mac(key))
@@ -408,11 +473,37 @@ impl<T: AsRef<[u8]>, F: CryptoFactory> DecryptedJoinAcceptPayload<T, F> {
         self.mic() == self.calculate_mic(key)
     }
 
+    /// Verifies that the JoinAccept has correct MIC, giving both MICs if it does not.
+    pub fn validate_mic_verbose(&self, key: &AppKey) -> Result<(), MicMismatch> {
+        check_mic(self.mic(), self.calculate_mic(key))
+    }
+
     pub fn calculate_mic(&self, key: &AppKey) -> MIC {
         let d = self.0.as_ref();
         securityhelpers::calculate_mic(&d[..d.len() - MIC_LEN], self.1.new_mac(&key.0))
     }
 
+    /// Verifies that a LoRaWAN 1.1 JoinAccept with the OptNeg bit set has correct MIC.
+    ///
+    /// # Argument
+    ///
+    /// * key - the JSIntKey of the device.
+    /// * join_req_type - 0xFF for a JoinRequest, 0x00 to 0x02 for the RejoinRequest types.
+    /// * join_eui - the JoinEUI of the request being answered.
+    /// * dev_nonce - the DevNonce, or RJcount, of the request being answered.
+    pub fn validate_mic_v1_1(
+        &self,
+        key: &JSIntKey,
+        join_req_type: u8,
+        join_eui: &EUI64<[u8; 8]>,
+        dev_nonce: &DevNonce<[u8; 2]>,
+    ) -> bool {
+        let d = self.0.as_ref();
+        let version = JoinAcceptMicVersion::V1_1 { join_req_type, join_eui, dev_nonce };
+        self.mic()
+            == join_accept_mic(&self.1, &key.0, MHDR::new(d[0]), &d[1..d.len() - MIC_LEN], version)
+    }
+
     /// Computes the network session key for a given device.
     ///
     /// # Argument
@@ -526,6 +617,38 @@ pub enum CfList<'a> {
     FixedChannel(ChannelMask<9>),
 }
 
+impl CfList<'_> {
+    /// Encodes a CFList of the frequency type, as sent in a JoinAccept, and returns its length.
+    ///
+    /// Frequencies are given in Hz. Unused entries of the list are set to 0, which disables the
+    /// corresponding channels.
+    ///
+    /// # Arguments
+    ///
+    /// * freqs - up to 5 frequencies of channels to be added to the default ones, at most
+    ///   1677.7215 MHz.
+    /// * out - the buffer receiving the CFList, at least 16 bytes long.
+    pub fn encode_frequencies(
+        freqs: &[u32],
+        out: &mut [u8],
+    ) -> Result<usize, crate::creator::Error> {
+        // frequencies are encoded in 24 bits, in steps of 100 Hz
+        if freqs.len() > 5 || freqs.iter().any(|freq| freq / 100 > 0x00ff_ffff) {
+            return Err(crate::creator::Error::InvalidChannelList);
+        }
+        if out.len() < CF_LIST_LEN {
+            return Err(crate::creator::Error::BufferTooShort);
+        }
+        out[..CF_LIST_LEN].fill(0);
+        for (i, freq) in freqs.iter().enumerate() {
+            out[i * 3..i * 3 + 3].copy_from_slice(&(freq / 100).to_le_bytes()[..3]);
+        }
+        // CFListType 0: frequencies
+        out[CF_LIST_LEN - 1] = 0;
+        Ok(CF_LIST_LEN)
+    }
+}
+
 impl<T: AsRef<[u8]>, F> DecryptedJoinAcceptPayload<T, F> {
     /// Gives the app nonce of the JoinAccept.
     pub fn app_nonce(&self) -> AppNonce<&[u8]> {
@@ -618,7 +741,12 @@ pub trait DataHeader {
 
     /// Gives the FHDR of the DataPayload.
     fn fhdr(&self) -> FHDR<'_> {
-        FHDR::new_from_raw(&self.as_data_bytes()[1..(1 + self.fhdr_length())], self.is_uplink())
+        FHDR::new_from_raw(self.fhdr_bytes(), self.is_uplink())
+    }
+
+    /// Gives the raw bytes of the FHDR (DevAddr, FCtrl, FCnt and FOpts).
+    fn fhdr_bytes(&self) -> &[u8] {
+        &self.as_data_bytes()[1..(1 + self.fhdr_length())]
     }
 
     /// Gives whether the frame is confirmed
@@ -707,10 +835,34 @@ impl<T: AsRef<[u8]>, F: CryptoFactory> EncryptedDataPayload<T, F> {
         self.mic() == self.calculate_mic(key, fcnt)
     }
 
+    /// Verifies that the DataPayload has correct MIC, giving both MICs if it does not.
+    pub fn validate_mic_verbose(&self, key: &AES128, fcnt: u32) -> Result<(), MicMismatch> {
+        check_mic(self.mic(), self.calculate_mic(key, fcnt))
+    }
+
     fn calculate_mic(&self, key: &AES128, fcnt: u32) -> MIC {
         let d = self.0.as_ref();
         securityhelpers::calculate_data_mic(&d[..d.len() - MIC_LEN], self.1.new_mac(key), fcnt)
     }
+
+    /// Gives the FRMPayload as transmitted, ie: still encrypted, or None if there is no FPort
+    /// (and thus no FRMPayload).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let data = vec![
+    ///     0x40, 0x04, 0x03, 0x02, 0x01, 0x80, 0x01, 0x00, 0x01, 0xa6, 0x94, 0x64, 0x26, 0x15, 0xd6,
+    ///     0xc3, 0xb5, 0x82,
+    /// ];
+    /// let phy = lorawan::parser::EncryptedDataPayload::new(data).unwrap();
+    /// assert_eq!(phy.encrypted_frm_payload(), Some(&[0xa6, 0x94, 0x64, 0x26, 0x15][..]));
+    /// ```
+    pub fn encrypted_frm_payload(&self) -> Option<&[u8]> {
+        let start = MHDR_LEN + self.fhdr_length() + FPORT_LEN;
+        let d = self.0.as_ref();
+        self.f_port().map(|_| &d[start..d.len() - MIC_LEN])
+    }
 }
 
 impl<T: AsRef<[u8]> + AsMut<[u8]>, F: CryptoFactory> EncryptedDataPayload<T, F> {
@@ -802,6 +954,38 @@ fn compute_fcnt(old_fcnt: u32, fcnt: u16) -> u32 {
     ((old_fcnt >> 16) << 16) ^ u32::from(fcnt)
 }
 
+/// Maximum allowed difference between the last accepted and a newly received frame counter.
+pub const MAX_FCNT_GAP: u32 = 16384;
+
+/// Reconstructs the full 32-bit frame counter from the 16 bits transmitted in the FHDR.
+///
+/// Intended for network servers, which must reject frames whose counter jumped by more than
+/// [`MAX_FCNT_GAP`] compared to the last accepted one.
+///
+/// # Argument
+///
+/// * last_fcnt - the last accepted full frame counter.
+/// * fcnt - the 16 bit frame counter from the FHDR of the received frame.
+///
+/// # Examples
+///
+/// ```
+/// use lorawan::parser::{reconstruct_fcnt, Error};
+/// assert_eq!(reconstruct_fcnt(0x1fffe, 0x0003), Ok(0x20003));
+/// assert_eq!(reconstruct_fcnt(10, 20_000), Err(Error::FCntGapTooLarge));
+/// ```
+pub fn reconstruct_fcnt(last_fcnt: u32, fcnt: u16) -> Result<u32, Error> {
+    let mut full_fcnt = compute_fcnt(last_fcnt, fcnt);
+    if full_fcnt < last_fcnt {
+        // the 16 LSB have rolled over
+        full_fcnt = full_fcnt.wrapping_add(1 << 16);
+    }
+    if full_fcnt.wrapping_sub(last_fcnt) > MAX_FCNT_GAP {
+        return Err(Error::FCntGapTooLarge);
+    }
+    Ok(full_fcnt)
+}
+
 /// DecryptedDataPayload represents a decrypted DataPayload.
 ///
 /// It can be built either directly through the [new](#method.new) or using the
@@ -917,6 +1101,38 @@ where
     }
 }
 
+/// Gives the DevAddr of a data frame without parsing it, eg: to route frames.
+///
+/// Returns None if the frame is not a data frame or is too short to contain the FHDR and MIC.
+/// Nothing else is validated, in particular not the MIC.
+///
+/// # Examples
+///
+/// ```
+/// let data = [
+///     0x40, 0x04, 0x03, 0x02, 0x01, 0x80, 0x01, 0x00, 0x01, 0xa6, 0x94, 0x64, 0x26, 0x15, 0xd6,
+///     0xc3, 0xb5, 0x82,
+/// ];
+/// let dev_addr = lorawan::parser::peek_dev_addr(&data).unwrap();
+/// assert_eq!(dev_addr.as_ref(), &[0x04, 0x03, 0x02, 0x01]);
+/// ```
+pub fn peek_dev_addr(data: &[u8]) -> Option<DevAddr<[u8; 4]>> {
+    if data.len() < PHY_PAYLOAD_MIN_LEN || 5 + fhdr_length(data[5]) > data.len() {
+        return None;
+    }
+    match MHDR(data[0]).mtype() {
+        MType::UnconfirmedDataUp
+        | MType::ConfirmedDataUp
+        | MType::UnconfirmedDataDown
+        | MType::ConfirmedDataDown => {
+            let mut res = [0; 4];
+            res.copy_from_slice(&data[MHDR_LEN..MHDR_LEN + 4]);
+            Some(DevAddr::from(res))
+        }
+        _ => None,
+    }
+}
+
 /// MHDR represents LoRaWAN MHDR.
 #[derive(Debug, PartialEq, Eq)]
 pub struct MHDR(u8);
@@ -956,6 +1172,12 @@ impl From<u8> for MHDR {
     }
 }
 
+impl From<MHDR> for u8 {
+    fn from(v: MHDR) -> Self {
+        v.0
+    }
+}
+
 /// MType gives the possible message types of the PhyPayload.
 #[derive(Debug, PartialEq, Eq)]
 pub enum MType {
@@ -1016,6 +1238,47 @@ impl<T: AsRef<[u8]>> DevAddr<T> {
     pub fn as_ref(&self) -> &[u8] {
         self.0.as_ref()
     }
+
+    /// Splits the DevAddr according to the addressing scheme of the LoRaWAN Backend Interfaces,
+    /// where the number of leading 1 bits gives the type, and thus the lengths of the NwkID and
+    /// NwkAddr which follow.
+    ///
+    /// Returns None for the reserved prefix `0xff`, which belongs to no type.
+    pub fn parts(&self) -> Option<DevAddrParts> {
+        let bytes = self.0.as_ref();
+        let addr = u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+        let addr_type = addr.leading_ones();
+        let nwk_id_bits = *NWK_ID_BITS.get(addr_type as usize)?;
+        let nwk_addr_bits = 32 - (addr_type + 1) - nwk_id_bits;
+        Some(DevAddrParts {
+            addr_type: addr_type as u8,
+            nwk_id: (addr >> nwk_addr_bits) & ((1 << nwk_id_bits) - 1),
+            nwk_addr: addr & ((1 << nwk_addr_bits) - 1),
+        })
+    }
+}
+
+// Length of the NwkID for each type of DevAddr, the NwkAddr takes the remaining bits
+const NWK_ID_BITS: [u32; 8] = [6, 6, 9, 11, 12, 13, 15, 17];
+
+/// The parts of a DevAddr, see [`DevAddr::parts`].
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub struct DevAddrParts {
+    /// The type of the address, between 0 and 7, which is also the type of the NetID of the
+    /// network it belongs to.
+    pub addr_type: u8,
+    /// The NwkID, ie: the least significant bits of the NetID.
+    pub nwk_id: u32,
+    /// The network address of the device.
+    pub nwk_addr: u32,
+}
+
+impl DevAddrParts {
+    /// Number of bits of the NwkID for the type of the address.
+    pub fn nwk_id_len(&self) -> u32 {
+        NWK_ID_BITS[usize::from(self.addr_type)]
+    }
 }
 
 impl From<DevAddr<[u8; 4]>> for u32 {
@@ -1035,6 +1298,16 @@ fixed_len_struct! {
     struct NwkAddr[3];
 }
 
+impl<T: AsRef<[u8]>> NwkAddr<T> {
+    /// Gives the NetID as a number. How many of its LSB form the NwkID of the DevAddrs it assigns
+    /// depends on its type, see [`DevAddr::parts`].
+    pub fn value(&self) -> u32 {
+        let bytes = self.0.as_ref();
+        // the NetID is transmitted little endian
+        u32::from_le_bytes([bytes[0], bytes[1], bytes[2], 0])
+    }
+}
+
 /// FHDR represents FHDR from DataPayload.
 #[derive(Debug, PartialEq, Eq)]
 pub struct FHDR<'a>(&'a [u8], bool);
@@ -0,0 +1,147 @@
+//! Provides types for the LoRaWAN Relay specification (TS011) forwarding messages.
+//!
+//! A relay forwards end-device uplinks to the network by embedding them, together with some
+//! metadata about their reception, in the FRMPayload of its own data uplinks on [`RELAY_FPORT`].
+//! As such the forwarded frames are encrypted and protected by the MIC of the relay's session,
+//! which is handled by the regular [`DataPayloadCreator`](crate::creator::DataPayloadCreator) and
+//! [`EncryptedDataPayload`](crate::parser::EncryptedDataPayload).
+use crate::parser::Error;
+use crate::types::Frequency;
+
+/// FPort reserved for the relay forwarding messages.
+pub const RELAY_FPORT: u8 = 226;
+
+const METADATA_LEN: usize = 3;
+const FREQUENCY_LEN: usize = 3;
+const HEADER_LEN: usize = METADATA_LEN + FREQUENCY_LEN;
+
+/// Reception metadata of an end-device uplink forwarded by a relay.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub struct UplinkMetadata {
+    /// Data rate the uplink was received with.
+    pub data_rate: u8,
+    /// SNR of the uplink in dB, between -20 and 11.
+    pub snr: i8,
+    /// RSSI of the uplink in dBm, between -142 and -15.
+    pub rssi: i16,
+    /// Wake On Radio channel the uplink was received on.
+    pub wor_channel: u8,
+}
+
+impl UplinkMetadata {
+    // |   RFU   | WORChannel | UplinkRSSI | UplinkSNR |   DR   |
+    // | 6 bits  |   2 bits   |   7 bits   |  5 bits   | 4 bits |
+    fn from_bytes(bytes: &[u8]) -> Self {
+        let raw = u32::from(bytes[0]) | u32::from(bytes[1]) << 8 | u32::from(bytes[2]) << 16;
+        Self {
+            data_rate: (raw & 0x0f) as u8,
+            snr: ((raw >> 4) & 0x1f) as i8 - 20,
+            rssi: -(((raw >> 9) & 0x7f) as i16) - 15,
+            wor_channel: ((raw >> 16) & 0x03) as u8,
+        }
+    }
+
+    fn to_bytes(self) -> Result<[u8; METADATA_LEN], Error> {
+        if self.data_rate > 0x0f
+            || !(-20..=11).contains(&self.snr)
+            || !(-142..=-15).contains(&self.rssi)
+            || self.wor_channel > 0x03
+        {
+            return Err(Error::InvalidData);
+        }
+        let raw = u32::from(self.data_rate)
+            | ((self.snr + 20) as u32) << 4
+            | ((-self.rssi - 15) as u32) << 9
+            | u32::from(self.wor_channel) << 16;
+        let bytes = raw.to_le_bytes();
+        Ok([bytes[0], bytes[1], bytes[2]])
+    }
+}
+
+/// ForwardUplinkReq represents an end-device uplink wrapped by a relay, as found in the
+/// (decrypted) FRMPayload of a relay uplink on [`RELAY_FPORT`].
+#[derive(Debug, PartialEq, Eq)]
+pub struct ForwardUplinkReq<T: AsRef<[u8]>>(T);
+
+impl<T: AsRef<[u8]>> ForwardUplinkReq<T> {
+    /// Creates a ForwardUplinkReq from the bytes of the FRMPayload, checking that they are long
+    /// enough to contain the metadata, the frequency and a PHYPayload.
+    ///
+    /// # Argument
+    ///
+    /// * data - the bytes of the (decrypted) FRMPayload.
+    pub fn new(data: T) -> Result<Self, Error> {
+        if data.as_ref().len() <= HEADER_LEN {
+            return Err(Error::InvalidPayload);
+        }
+        Ok(Self(data))
+    }
+
+    /// Gives the reception metadata of the forwarded uplink.
+    pub fn metadata(&self) -> UplinkMetadata {
+        UplinkMetadata::from_bytes(&self.0.as_ref()[..METADATA_LEN])
+    }
+
+    /// Gives the frequency the forwarded uplink was received on.
+    pub fn frequency(&self) -> Frequency<'_> {
+        Frequency::new_from_raw(&self.0.as_ref()[METADATA_LEN..HEADER_LEN])
+    }
+
+    /// Gives the PHYPayload of the forwarded end-device uplink.
+    pub fn phy_payload(&self) -> &[u8] {
+        &self.0.as_ref()[HEADER_LEN..]
+    }
+}
+
+/// ForwardUplinkReqCreator serves for creating the FRMPayload of a relay uplink forwarding an
+/// end-device uplink.
+///
+/// # Examples
+///
+/// ```
+/// use lorawan::relay::{ForwardUplinkReqCreator, UplinkMetadata};
+/// let mut buf = [0u8; 64];
+/// let metadata = UplinkMetadata { data_rate: 5, snr: 7, rssi: -80, wor_channel: 0 };
+/// let mut creator = ForwardUplinkReqCreator::new(&mut buf[..]);
+/// let payload = creator.build(&metadata, 868_100_000, &[0x40, 0x04, 0x03, 0x02, 0x01]).unwrap();
+/// ```
+pub struct ForwardUplinkReqCreator<D> {
+    data: D,
+}
+
+impl<D: AsMut<[u8]>> ForwardUplinkReqCreator<D> {
+    /// Creates a ForwardUplinkReqCreator writing into the provided buffer.
+    pub fn new(data: D) -> Self {
+        Self { data }
+    }
+
+    /// Builds the ForwardUplinkReq, returning the bytes to be used as FRMPayload of a relay
+    /// uplink on [`RELAY_FPORT`].
+    ///
+    /// # Argument
+    ///
+    /// * metadata - the reception metadata of the end-device uplink.
+    /// * frequency - the frequency in Hz the end-device uplink was received on.
+    /// * phy_payload - the PHYPayload of the end-device uplink.
+    pub fn build(
+        &mut self,
+        metadata: &UplinkMetadata,
+        frequency: u32,
+        phy_payload: &[u8],
+    ) -> Result<&[u8], Error> {
+        let d = self.data.as_mut();
+        let len = HEADER_LEN + phy_payload.len();
+        if phy_payload.is_empty() || d.len() < len {
+            return Err(Error::InvalidData);
+        }
+        let frequency = frequency / 100;
+        if frequency > 0x00ff_ffff {
+            return Err(Error::InvalidData);
+        }
+        d[..METADATA_LEN].copy_from_slice(&metadata.to_bytes()?);
+        d[METADATA_LEN..HEADER_LEN].copy_from_slice(&frequency.to_le_bytes()[..FREQUENCY_LEN]);
+        d[HEADER_LEN..len].copy_from_slice(phy_payload);
+        Ok(&d[..len])
+    }
+}
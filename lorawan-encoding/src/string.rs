@@ -6,6 +6,55 @@ pub extern crate std;
 
 pub use hex::FromHexError;
 
+/// Formats `bytes` (at most 16), LSB first if `lsb`, as a hex string. It uses a stack buffer and
+/// honors the padding options of the formatter.
+fn fmt_hex(
+    f: &mut core::fmt::Formatter<'_>,
+    bytes: &[u8],
+    lsb: bool,
+    upper: bool,
+) -> core::fmt::Result {
+    let digits = if upper {
+        b"0123456789ABCDEF"
+    } else {
+        b"0123456789abcdef"
+    };
+    let mut res = [0; 32];
+    for (i, b) in bytes.iter().enumerate() {
+        let i = if lsb {
+            bytes.len() - 1 - i
+        } else {
+            i
+        };
+        res[i * 2] = digits[usize::from(b >> 4)];
+        res[i * 2 + 1] = digits[usize::from(b & 0x0f)];
+    }
+    // only ASCII hex digits were written
+    f.pad(core::str::from_utf8(&res[..bytes.len() * 2]).unwrap())
+}
+
+macro_rules! impl_fmt_hex {
+    ($type:ty, $lsb:expr, $($generics:tt)*) => {
+        impl<$($generics)*> core::fmt::Display for $type {
+            fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+                fmt_hex(f, self.as_ref(), $lsb, false)
+            }
+        }
+
+        impl<$($generics)*> core::fmt::LowerHex for $type {
+            fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+                fmt_hex(f, self.as_ref(), $lsb, false)
+            }
+        }
+
+        impl<$($generics)*> core::fmt::UpperHex for $type {
+            fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+                fmt_hex(f, self.as_ref(), $lsb, true)
+            }
+        }
+    };
+}
+
 macro_rules! fixed_len_struct_impl_to_string_msb {
     (
         $type:ident,$size:expr;
@@ -20,16 +69,7 @@ macro_rules! fixed_len_struct_impl_to_string_msb {
             }
         }
 
-        #[cfg(feature = "with-to-string")]
-        impl core::fmt::Display for $type {
-            fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
-                let mut res = std::string::String::with_capacity($size * 2);
-                res.extend(std::iter::repeat('-').take($size * 2));
-                let slice = unsafe { &mut res.as_bytes_mut() };
-                hex::encode_to_slice(self.as_ref(), slice).unwrap();
-                write!(f, "{}", res)
-            }
-        }
+        impl_fmt_hex!($type, false,);
     };
     (
         $type:ident[$size:expr];
@@ -44,19 +84,7 @@ macro_rules! fixed_len_struct_impl_to_string_msb {
             }
         }
 
-        #[cfg(feature = "with-to-string")]
-        impl<T: AsRef<[u8]>> core::fmt::Display for $type<T> {
-            fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
-                // initialize the return string with the correct size and fill it with '-'
-                let mut res = std::string::String::with_capacity($size * 2);
-                res.extend(std::iter::repeat('-').take($size * 2));
-
-                // get the string as bytes so that we can call hex::encode_to_slice directly
-                let slice = unsafe { &mut res.as_bytes_mut() };
-                hex::encode_to_slice(self.as_ref(), slice).unwrap();
-                write!(f, "{}", res)
-            }
-        }
+        impl_fmt_hex!($type<T>, false, T: AsRef<[u8]>);
     };
 }
 
@@ -75,18 +103,7 @@ macro_rules! fixed_len_struct_impl_string_lsb {
             }
         }
 
-        #[cfg(feature = "with-to-string")]
-        impl core::fmt::Display for $type {
-            fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
-                let mut res = std::string::String::with_capacity($size * 2);
-                res.extend(std::iter::repeat('0').take($size * 2));
-                let slice = unsafe { &mut res.as_bytes_mut() };
-                self.as_ref().iter().rev().enumerate().for_each(|(i, b)| {
-                    hex::encode_to_slice(&[*b], &mut slice[i * 2..i * 2 + 2]).unwrap();
-                });
-                write!(f, "{}", res)
-            }
-        }
+        impl_fmt_hex!($type, true,);
     };
 }
 
@@ -122,6 +139,38 @@ fixed_len_struct_impl_to_string_msb! {
     AppSKey, 16;
 }
 
+fixed_len_struct_impl_to_string_msb! {
+    NwkKey, 16;
+}
+
+fixed_len_struct_impl_to_string_msb! {
+    FNwkSIntKey, 16;
+}
+
+fixed_len_struct_impl_to_string_msb! {
+    SNwkSIntKey, 16;
+}
+
+fixed_len_struct_impl_to_string_msb! {
+    NwkSEncKey, 16;
+}
+
+fixed_len_struct_impl_to_string_msb! {
+    JSIntKey, 16;
+}
+
+fixed_len_struct_impl_to_string_msb! {
+    JSEncKey, 16;
+}
+
+fixed_len_struct_impl_to_string_msb! {
+    AES128, 16;
+}
+
+fixed_len_struct_impl_to_string_msb! {
+    MIC, 4;
+}
+
 fixed_len_struct_impl_string_lsb! {
     DevEui, 8;
 }
@@ -130,6 +179,42 @@ fixed_len_struct_impl_string_lsb! {
     AppEui, 8;
 }
 
+/// Serde helpers (de)serializing a [`DevEui`] or [`AppEui`] as the LSB hex string of their
+/// `Display` and `FromStr` implementations in every format. For use with `#[serde(with)]`, when
+/// binary formats should not get the byte array they otherwise do.
+///
+/// # Examples
+///
+/// ```
+/// use lorawan::keys::DevEui;
+///
+/// #[derive(serde::Serialize, serde::Deserialize)]
+/// struct Config {
+///     // serialized as "0011223344556677"
+///     #[serde(with = "lorawan::string::eui_hex")]
+///     dev_eui: DevEui,
+/// }
+/// ```
+#[cfg(feature = "serde")]
+pub mod eui_hex {
+    use super::FromHexError;
+    use crate::keys::HexVisitor;
+    use serde::{Deserializer, Serializer};
+
+    pub fn serialize<T: core::fmt::Display, S: Serializer>(
+        eui: &T,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(eui)
+    }
+
+    pub fn deserialize<'de, T: core::str::FromStr<Err = FromHexError>, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<T, D::Error> {
+        deserializer.deserialize_str(HexVisitor(core::marker::PhantomData))
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -157,6 +242,37 @@ mod test {
         );
     }
 
+    #[test]
+    fn test_aes128_from_str() {
+        let key = AES128::from_str("00112233445566778899aabbccddeeff").unwrap();
+        assert_eq!(
+            key,
+            AES128([
+                0x00, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88, 0x99, 0xAA, 0xBB, 0xCC, 0xDD,
+                0xEE, 0xFF
+            ])
+        );
+        assert_eq!(key.to_string(), "00112233445566778899aabbccddeeff");
+        assert_eq!(AES128::from_str("0011"), Err(FromHexError::InvalidStringLength));
+        assert_eq!(
+            AES128::from_str("00112233445566778899aabbccddeefg"),
+            Err(FromHexError::InvalidHexCharacter { c: 'g', index: 31 })
+        );
+    }
+
+    #[test]
+    fn test_mic_from_str() {
+        let mic = MIC::from_str("d6c3b582").unwrap();
+        assert_eq!(mic, MIC([0xd6, 0xc3, 0xb5, 0x82]));
+        assert_eq!(mic.to_string(), "d6c3b582");
+        assert_eq!(MIC::from_str("d6c3b58"), Err(FromHexError::OddLength));
+        assert_eq!(MIC::from_str("d6c3b5"), Err(FromHexError::InvalidStringLength));
+        assert_eq!(
+            MIC::from_str("d6c3b5z2"),
+            Err(FromHexError::InvalidHexCharacter { c: 'z', index: 6 })
+        );
+    }
+
     #[test]
     fn test_deveui_to_string() {
         let deveui = DevEui::from([0xf0, 0xde, 0xbc, 0x9a, 0x78, 0x56, 0x34, 0x12]);
@@ -169,6 +285,24 @@ mod test {
         assert_eq!(deveui, DevEui::from([0xf0, 0xde, 0xbc, 0x9a, 0x78, 0x56, 0x34, 0x12]));
     }
 
+    #[test]
+    fn test_deveui_round_trip() {
+        let s = "123456789abcdef0";
+        assert_eq!(DevEui::from_str(s).unwrap().to_string(), s);
+        assert_eq!(AppEui::from_str(s).unwrap().to_string(), s);
+    }
+
+    #[test]
+    fn test_hex_formatting() {
+        let deveui = DevEui::from([0xf0, 0xde, 0xbc, 0x9a, 0x78, 0x56, 0x34, 0x12]);
+        assert_eq!(crate::extra::std::format!("{deveui:x}"), "123456789abcdef0");
+        assert_eq!(crate::extra::std::format!("{deveui:X}"), "123456789ABCDEF0");
+        let appkey = AppKey::from_str("00112233445566778899aabbccddeeff").unwrap();
+        assert_eq!(crate::extra::std::format!("{appkey:X}"), "00112233445566778899AABBCCDDEEFF");
+        let devaddr = DevAddr::from([0x26, 0x01, 0x1b, 0xda]);
+        assert_eq!(crate::extra::std::format!("{devaddr:>10}"), "  26011bda");
+    }
+
     #[test]
     fn test_deveui_from_small_str() {
         let result = DevEui::from_str("123456789abcd");
@@ -180,4 +314,24 @@ mod test {
         let result = DevEui::from_str("123456789abcdef000");
         assert_eq!(result, Err(FromHexError::InvalidStringLength));
     }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_deveui_serde_hex() {
+        #[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+        struct Hex(#[serde(with = "eui_hex")] DevEui);
+
+        let deveui = Hex(DevEui::from([0xf0, 0xde, 0xbc, 0x9a, 0x78, 0x56, 0x34, 0x12]));
+        let serialized = serde_json::to_string(&deveui).unwrap();
+        assert_eq!(serialized, "\"123456789abcdef0\"");
+        assert_eq!(serde_json::from_str::<Hex>(&serialized).unwrap(), deveui);
+        assert!(serde_json::from_str::<Hex>("\"123456789abcd\"").is_err());
+
+        // binary formats get the string too
+        let mut cbor = std::vec::Vec::new();
+        ciborium::into_writer(&deveui, &mut cbor).unwrap();
+        assert_eq!(cbor[0], 0x70); // text string of 16 bytes
+        assert_eq!(&cbor[1..], b"123456789abcdef0");
+        assert_eq!(ciborium::from_reader::<Hex, _>(&cbor[..]).unwrap(), deveui);
+    }
 }
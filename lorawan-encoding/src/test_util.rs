@@ -0,0 +1,182 @@
+//! Provides a deterministic [`CryptoFactory`] for tests which records every cryptographic
+//! operation together with the key that was used for it.
+//!
+//! The "crypto" implemented here is NOT secure and is only meant for asserting that higher-level
+//! logic uses the right keys.
+pub extern crate std;
+
+use super::keys::*;
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::vec::Vec;
+
+/// Kind of operation performed through a [`RecordingFactory`].
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Operation {
+    /// A single block was encrypted.
+    Encrypt,
+    /// A single block was decrypted.
+    Decrypt,
+    /// A MAC was computed.
+    Mac,
+}
+
+/// Single entry in the call log of a [`RecordingFactory`].
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Call {
+    pub operation: Operation,
+    pub key: AES128,
+}
+
+type Log = Rc<RefCell<Vec<Call>>>;
+
+/// Test-only [`CryptoFactory`] which records every operation performed with the encrypters,
+/// decrypters and MAC calculators it creates.
+///
+/// Clones share the same call log.
+///
+/// # Examples
+///
+/// ```
+/// use lorawan::keys::{CryptoFactory, Encrypter, AES128};
+/// use lorawan::test_util::{Call, Operation, RecordingFactory};
+///
+/// let factory = RecordingFactory::default();
+/// let key = AES128([1; 16]);
+/// factory.new_enc(&key).encrypt_block(&mut [0; 16]);
+/// assert_eq!(factory.calls(), [Call { operation: Operation::Encrypt, key }]);
+/// ```
+#[derive(Debug, Default, Clone)]
+pub struct RecordingFactory {
+    log: Log,
+}
+
+impl RecordingFactory {
+    /// Returns a copy of all recorded calls, in the order they happened.
+    pub fn calls(&self) -> Vec<Call> {
+        self.log.borrow().clone()
+    }
+
+    /// Returns how many operations of the given kind were recorded.
+    pub fn count(&self, operation: Operation) -> usize {
+        self.log.borrow().iter().filter(|c| c.operation == operation).count()
+    }
+
+    /// Clears the call log.
+    pub fn clear(&self) {
+        self.log.borrow_mut().clear()
+    }
+}
+
+impl CryptoFactory for RecordingFactory {
+    type E = RecordingCipher;
+    type D = RecordingCipher;
+    type M = RecordingMac;
+
+    fn new_enc(&self, key: &AES128) -> Self::E {
+        RecordingCipher { key: *key, log: self.log.clone() }
+    }
+
+    fn new_dec(&self, key: &AES128) -> Self::D {
+        RecordingCipher { key: *key, log: self.log.clone() }
+    }
+
+    fn new_mac(&self, key: &AES128) -> Self::M {
+        RecordingMac { key: *key, state: key.0, log: self.log.clone() }
+    }
+}
+
+/// Block "cipher" created by [`RecordingFactory`]. XORs the block with the key.
+#[derive(Debug)]
+pub struct RecordingCipher {
+    key: AES128,
+    log: Log,
+}
+
+impl RecordingCipher {
+    fn apply(&self, operation: Operation, block: &mut [u8]) {
+        for (b, k) in block.iter_mut().zip(self.key.0.iter()) {
+            *b ^= k;
+        }
+        self.log.borrow_mut().push(Call { operation, key: self.key });
+    }
+}
+
+impl Encrypter for RecordingCipher {
+    fn encrypt_block(&self, block: &mut [u8]) {
+        self.apply(Operation::Encrypt, block)
+    }
+}
+
+impl Decrypter for RecordingCipher {
+    fn decrypt_block(&self, block: &mut [u8]) {
+        self.apply(Operation::Decrypt, block)
+    }
+}
+
+/// MAC calculator created by [`RecordingFactory`]. Folds the input into the key with XOR.
+#[derive(Debug)]
+pub struct RecordingMac {
+    key: AES128,
+    state: [u8; 16],
+    log: Log,
+}
+
+impl Mac for RecordingMac {
+    fn input(&mut self, data: &[u8]) {
+        for (i, b) in data.iter().enumerate() {
+            self.state[i % 16] = self.state[i % 16].rotate_left(1) ^ b;
+        }
+    }
+
+    fn reset(&mut self) {
+        self.state = self.key.0;
+    }
+
+    fn result(self) -> [u8; 16] {
+        self.log.borrow_mut().push(Call { operation: Operation::Mac, key: self.key });
+        self.state
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::creator::DataPayloadCreator;
+
+    #[test]
+    fn test_recording_factory_logs_data_payload_build() {
+        let factory = RecordingFactory::default();
+        let nwk_skey = NwkSKey::from([2; 16]);
+        let app_skey = AppSKey::from([1; 16]);
+        let mut buf = [0u8; 64];
+        let mut phy = DataPayloadCreator::new(&mut buf[..]).unwrap();
+        phy.set_f_port(1);
+        phy.build(&[0; 20], &[], &nwk_skey, &app_skey, &factory).unwrap();
+
+        let expected = [
+            // 20 bytes of FRMPayload need two blocks of keystream
+            Call { operation: Operation::Encrypt, key: *app_skey.inner() },
+            Call { operation: Operation::Encrypt, key: *app_skey.inner() },
+            Call { operation: Operation::Mac, key: *nwk_skey.inner() },
+        ];
+        assert_eq!(factory.calls(), expected);
+        assert_eq!(factory.count(Operation::Encrypt), 2);
+        assert_eq!(factory.count(Operation::Decrypt), 0);
+
+        factory.clear();
+        assert!(factory.calls().is_empty());
+    }
+
+    #[test]
+    fn test_recording_factory_is_deterministic() {
+        let key = AES128([7; 16]);
+        let mac = |data: &[u8]| {
+            let mut mac = RecordingFactory::default().new_mac(&key);
+            mac.input(data);
+            mac.result()
+        };
+        assert_eq!(mac(&[1, 2, 3]), mac(&[1, 2, 3]));
+        assert_ne!(mac(&[1, 2, 3]), mac(&[3, 2, 1]));
+    }
+}
@@ -160,6 +160,16 @@ impl DataRateRange {
         Ok(Self::new_from_raw(byte))
     }
 
+    /// Constructs a new DataRateRange from the lowest and highest data rate allowed.
+    ///
+    /// MinDR is encoded in the low nibble and MaxDR in the high nibble.
+    pub fn from_min_max(min: u8, max: u8) -> Result<DataRateRange, Error> {
+        if min > 0x0f || max > 0x0f {
+            return Err(Error::InvalidDataRateRange);
+        }
+        Self::new((max << 4) | min)
+    }
+
     /// Check if the byte can be used to create DataRateRange.
     pub fn can_build_from(byte: u8) -> Result<(), Error> {
         if (byte >> 4) < (byte & 0x0f) {
@@ -182,6 +192,11 @@ impl DataRateRange {
     pub fn raw_value(&self) -> u8 {
         self.0
     }
+
+    /// Whether the data rate is within the range (inclusive).
+    pub fn contains(&self, data_rate: u8) -> bool {
+        (self.min_data_rate()..=self.max_data_rate()).contains(&data_rate)
+    }
 }
 
 impl From<u8> for DataRateRange {
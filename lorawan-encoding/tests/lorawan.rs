@@ -4,7 +4,7 @@ use lorawan::keys::*;
 use lorawan::maccommandcreator::*;
 use lorawan::maccommands::*;
 use lorawan::parser::*;
-use lorawan::types::{DLSettings, Frequency};
+use lorawan::types::{ChannelMask, DLSettings, Frequency};
 
 fn phy_join_request_payload() -> Vec<u8> {
     let mut res = Vec::new();
@@ -160,6 +160,18 @@ fn test_parse_join_request_payload() {
     );
 }
 
+#[test]
+fn test_join_request_join_eui_allowlist() {
+    let join_request = JoinRequestPayload::new(phy_join_request_payload()).unwrap();
+    let join_eui = AppEui::from([0x04, 0x03, 0x02, 0x01, 0x04, 0x03, 0x02, 0x01]);
+    assert_eq!(join_request.join_eui(), join_eui);
+
+    let other = AppEui::from([0x01; 8]);
+    assert!(join_request.join_eui_allowed(&[other, join_eui]));
+    assert!(!join_request.join_eui_allowed(&[other]));
+    assert!(!join_request.join_eui_allowed(&[]));
+}
+
 #[test]
 fn test_parse_join_accept_payload() {
     let phy = parse(phy_join_accept_payload());
@@ -197,6 +209,47 @@ fn test_parse_data_payload_no_panic_when_too_short_packet() {
     assert_eq!(phy.err(), Some(lorawan::parser::Error::InvalidData));
 }
 
+#[test]
+fn test_dev_addr_parts() {
+    // type 0, eg: The Things Network with NetID 0x000013
+    let parts = DevAddr::from(0x26011bda).parts().unwrap();
+    assert_eq!(parts, DevAddrParts { addr_type: 0, nwk_id: 0x13, nwk_addr: 0x11bda });
+    assert_eq!(parts.nwk_id, u32::from(DevAddr::from(0x26011bda).nwk_id()));
+    // type 3 with an 11 bits NwkID and 17 bits NwkAddr
+    let parts = DevAddr::from(0xe02c0001).parts().unwrap();
+    assert_eq!(parts, DevAddrParts { addr_type: 3, nwk_id: 0x16, nwk_addr: 0x1 });
+    assert_eq!(parts.nwk_id_len(), 11);
+    // type 6 with a 15 bits NwkID and 10 bits NwkAddr
+    let parts = DevAddr::from(0xfc001234).parts().unwrap();
+    assert_eq!(parts, DevAddrParts { addr_type: 6, nwk_id: 0x4, nwk_addr: 0x234 });
+    // type 7 with a 17 bits NwkID and 7 bits NwkAddr
+    let parts = DevAddr::from(0xfe000abc).parts().unwrap();
+    assert_eq!(parts, DevAddrParts { addr_type: 7, nwk_id: 0x15, nwk_addr: 0x3c });
+    // the 0xff prefix is reserved
+    assert_eq!(DevAddr::from(0xff000000).parts(), None);
+}
+
+#[test]
+fn test_peek_dev_addr() {
+    let data = phy_dataup_payload();
+    assert_eq!(peek_dev_addr(&data), DevAddr::new([4, 3, 2, 1]));
+
+    // Downlinks carry the DevAddr at the same place
+    let mut data = phy_dataup_payload();
+    data[0] = 0xa0;
+    assert_eq!(peek_dev_addr(&data), DevAddr::new([4, 3, 2, 1]));
+}
+
+#[test]
+fn test_peek_dev_addr_is_none_when_too_short_or_not_data() {
+    assert_eq!(peek_dev_addr(&[0x40, 0x04, 0x03, 0x02, 0x01]), None);
+    assert_eq!(peek_dev_addr(&[]), None);
+    // FOptsLen claims more bytes than the frame has
+    let data = [0x40, 0x04, 0x03, 0x02, 0x01, 0x85, 0x01, 0x00, 0xd6, 0xc3, 0xb5, 0x82];
+    assert_eq!(peek_dev_addr(&data), None);
+    assert_eq!(peek_dev_addr(&phy_join_request_payload()), None);
+}
+
 #[test]
 fn test_new_join_accept_payload_too_short() {
     let mut bytes = phy_join_accept_payload();
@@ -225,6 +278,44 @@ fn test_new_join_accept_c_f_list_empty() {
     assert_eq!(decrypted_phy.c_f_list(), None);
 }
 
+#[test]
+fn test_c_f_list_encode_frequencies_round_trip() {
+    let freqs = [867_100_000, 867_300_000, 867_500_000, 867_700_000, 867_900_000];
+    let mut out = [0xff; 17];
+    assert_eq!(CfList::encode_frequencies(&freqs, &mut out), Ok(16));
+    assert_eq!(out[15..], [0, 0xff]);
+
+    let decrypted_phy = new_decrypted_join_accept();
+    let Some(CfList::DynamicChannel(decoded)) = decrypted_phy.c_f_list() else {
+        panic!("expected a frequency CFList");
+    };
+    for (i, (freq, expected)) in decoded.iter().zip(freqs).enumerate() {
+        let chunk = &out[i * 3..i * 3 + 3];
+        assert_eq!(chunk, freq.as_ref());
+        assert_eq!(Frequency::new_from_raw(chunk).value(), expected);
+    }
+
+    // the highest frequency which can be encoded, and the first one above it
+    let mut out = [0; 16];
+    assert_eq!(CfList::encode_frequencies(&[1_677_721_500], &mut out), Ok(16));
+    assert_eq!(Frequency::new_from_raw(&out[..3]).value(), 1_677_721_500);
+    assert_eq!(
+        CfList::encode_frequencies(&[867_100_000, 1_677_721_600], &mut out),
+        Err(lorawan::creator::Error::InvalidChannelList)
+    );
+}
+
+#[test]
+fn test_c_f_list_encode_frequencies_partial() {
+    let mut out = [0xff; 16];
+    assert_eq!(CfList::encode_frequencies(&[868_500_000], &mut out), Ok(16));
+    assert_eq!(Frequency::new_from_raw(&out[..3]).value(), 868_500_000);
+    assert_eq!(out[3..], [0; 13]);
+
+    assert!(CfList::encode_frequencies(&[868_500_000; 6], &mut out).is_err());
+    assert!(CfList::encode_frequencies(&[868_500_000], &mut out[..15]).is_err());
+}
+
 #[test]
 fn test_join_accept_app_nonce_extraction() {
     let decrypted_phy = new_decrypted_join_accept();
@@ -232,6 +323,14 @@ fn test_join_accept_app_nonce_extraction() {
     assert_eq!(decrypted_phy.app_nonce(), AppNonce::new(&expected[..]).unwrap());
 }
 
+#[test]
+fn test_join_accept_net_id_extraction() {
+    let decrypted_phy = new_decrypted_join_accept();
+    let expected = [7, 8, 9];
+    assert_eq!(decrypted_phy.net_id(), NwkAddr::new(&expected[..]).unwrap());
+    assert_eq!(decrypted_phy.net_id().value(), 0x090807);
+}
+
 #[test]
 fn test_join_accept_rx_delay_extraction() {
     let decrypted_phy = new_decrypted_join_accept();
@@ -275,6 +374,198 @@ fn test_mic_extraction() {
     assert_eq!(phy.unwrap().mic(), MIC([0xd6, 0xc3, 0xb5, 0x82]));
 }
 
+#[test]
+fn test_key_deref_to_aes128() {
+    fn first_byte(key: &AES128) -> u8 {
+        key.0[0]
+    }
+    let app_key = AppKey::from([1; 16]);
+    let nwk_skey = NwkSKey::from([2; 16]);
+    let app_skey = AppSKey::from([3; 16]);
+    assert_eq!(first_byte(&app_key), 1);
+    assert_eq!(first_byte(&nwk_skey), 2);
+    assert_eq!(first_byte(&app_skey), 3);
+    assert_eq!(&*app_key, app_key.inner());
+}
+
+/// A deterministic xorshift RNG, seeded for reproducible tests.
+#[cfg(feature = "rand")]
+struct SeededRng(u64);
+
+#[cfg(feature = "rand")]
+impl rand_core::RngCore for SeededRng {
+    fn next_u32(&mut self) -> u32 {
+        self.next_u64() as u32
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        rand_core::impls::fill_bytes_via_next(self, dest)
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand_core::Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+#[test]
+#[cfg(feature = "rand")]
+fn test_generate_keys_and_euis() {
+    let app_key = AppKey::generate(&mut SeededRng(1));
+    // the same seed gives the same key
+    assert_eq!(app_key, AppKey::generate(&mut SeededRng(1)));
+    assert_ne!(app_key, AppKey::generate(&mut SeededRng(2)));
+    // the key is made of the bytes produced by the RNG
+    let mut expected = [0u8; 16];
+    rand_core::RngCore::fill_bytes(&mut SeededRng(1), &mut expected);
+    assert_eq!(app_key.into_bytes(), expected);
+
+    let mut rng = SeededRng(1);
+    let dev_eui = DevEui::generate(&mut rng);
+    // generating from the same RNG gives different values
+    assert_ne!(dev_eui, DevEui::generate(&mut rng));
+    assert_eq!(dev_eui.as_ref(), &expected[..8]);
+}
+
+#[test]
+fn test_keywrap_rfc3394_vector() {
+    // RFC 3394, 4.1 Wrap 128 bits of Key Data with a 128-bit KEK
+    let kek = AES128([
+        0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0a, 0x0b, 0x0c, 0x0d, 0x0e,
+        0x0f,
+    ]);
+    let key = AES128([
+        0x00, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88, 0x99, 0xaa, 0xbb, 0xcc, 0xdd, 0xee,
+        0xff,
+    ]);
+    let wrapped = [
+        0x1f, 0xa6, 0x8b, 0x0a, 0x81, 0x12, 0xb4, 0x47, 0xae, 0xf3, 0x4b, 0xd8, 0xfb, 0x5a, 0x7b,
+        0x82, 0x9d, 0x3e, 0x86, 0x23, 0x71, 0xd2, 0xcf, 0xe5,
+    ];
+    assert_eq!(keywrap::wrap(&DefaultFactory, &kek, &key), wrapped);
+    assert_eq!(keywrap::unwrap(&DefaultFactory, &kek, &wrapped), Ok(key));
+}
+
+#[test]
+fn test_keywrap_integrity_check() {
+    let kek = AES128([1; 16]);
+    let wrapped = keywrap::wrap(&DefaultFactory, &kek, &AppKey::from(app_key()));
+    assert_eq!(keywrap::unwrap(&DefaultFactory, &kek, &wrapped), Ok(AES128(app_key())));
+
+    let mut altered = wrapped;
+    altered[12] ^= 0x01;
+    assert_eq!(keywrap::unwrap(&DefaultFactory, &kek, &altered), Err(keywrap::IntegrityError));
+    assert_eq!(
+        keywrap::unwrap(&DefaultFactory, &AES128([2; 16]), &wrapped),
+        Err(keywrap::IntegrityError)
+    );
+}
+
+#[test]
+fn test_aes128_bytes_round_trip() {
+    let bytes = app_key();
+    let key = AES128::from(bytes);
+    assert_eq!(key.as_bytes(), &bytes);
+    assert_eq!(key.into_bytes(), bytes);
+    assert_eq!(<[u8; 16]>::from(key), bytes);
+}
+
+#[test]
+fn test_key_bytes_round_trip() {
+    let bytes = app_key();
+    let app_key = AppKey::from(bytes);
+    assert_eq!(app_key.as_bytes(), &bytes);
+    assert_eq!(AppKey::from(app_key.into_bytes()), app_key);
+    let nwk_skey = NwkSKey::from(bytes);
+    assert_eq!(<[u8; 16]>::from(nwk_skey), bytes);
+    let app_skey = AppSKey::from(bytes);
+    assert_eq!(app_skey.into_bytes(), bytes);
+}
+
+#[test]
+fn test_key_try_from_slice() {
+    let bytes = app_key();
+    assert_eq!(AppKey::try_from(&bytes[..]), Ok(AppKey::from(bytes)));
+    assert_eq!(NwkSKey::try_from(&bytes[..]), Ok(NwkSKey::from(bytes)));
+    assert_eq!(AppSKey::try_from(&bytes[..]), Ok(AppSKey::from(bytes)));
+    assert_eq!(AppKey::try_from(&bytes[..15]), Err(LengthError));
+}
+
+#[test]
+fn test_eui_try_from_slice() {
+    let bytes = [0x77, 0x66, 0x55, 0x44, 0x33, 0x22, 0x11, 0x00];
+    assert_eq!(DevEui::try_from(&bytes[..]), Ok(DevEui::from(bytes)));
+    assert_eq!(AppEui::try_from(&bytes[..]), Ok(AppEui::from(bytes)));
+    assert_eq!(DevEui::try_from(&bytes[..7]), Err(LengthError));
+}
+
+#[test]
+fn test_mic_try_from_slice() {
+    let bytes = &phy_dataup_payload()[..];
+    let mic = MIC::try_from(&bytes[bytes.len() - 4..]);
+
+    assert_eq!(mic, Ok(MIC([0xd6, 0xc3, 0xb5, 0x82])));
+}
+
+#[test]
+fn test_mic_try_from_short_slice() {
+    assert_eq!(MIC::try_from(&[0xd6, 0xc3, 0xb5][..]), Err(LengthError));
+}
+
+#[test]
+fn test_mic_try_from_long_slice() {
+    assert_eq!(MIC::try_from(&[0xd6, 0xc3, 0xb5, 0x82, 0x00][..]), Err(LengthError));
+}
+
+#[test]
+fn test_constant_time_eq() {
+    assert_eq!(MIC([0xd6, 0xc3, 0xb5, 0x82]), MIC([0xd6, 0xc3, 0xb5, 0x82]));
+    assert_ne!(MIC([0xd6, 0xc3, 0xb5, 0x82]), MIC([0xd6, 0xc3, 0xb5, 0x83]));
+    assert_ne!(MIC([0xd6, 0xc3, 0xb5, 0x82]), MIC([0x00, 0xc3, 0xb5, 0x82]));
+
+    assert_eq!(AES128(app_key()), AES128(app_key()));
+    let mut other = app_key();
+    other[15] ^= 0x80;
+    assert_ne!(AES128(app_key()), AES128(other));
+    assert_ne!(AppKey::from(app_key()), AppKey::from(other));
+}
+
+#[test]
+fn test_error_display() {
+    assert_eq!(lorawan::parser::Error::InvalidMic.to_string(), "invalid MIC");
+    assert_eq!(lorawan::creator::Error::BufferTooShort.to_string(), "buffer too short");
+    assert_eq!(lorawan::maccommands::Error::UnknownMacCommand.to_string(), "unknown MAC command");
+    assert_eq!(
+        lorawan::maccommandcreator::Error::MarginOutOfRange.to_string(),
+        "margin out of range"
+    );
+    assert_eq!(LengthError.to_string(), "invalid length");
+}
+
+#[test]
+fn test_reconstruct_fcnt_within_gap() {
+    assert_eq!(reconstruct_fcnt(5, 6), Ok(6));
+    assert_eq!(reconstruct_fcnt(0x1_0005, 0x4005), Ok(0x1_4005));
+    // the 16 LSB rolled over
+    assert_eq!(reconstruct_fcnt(0xfff0, 0x0010), Ok(0x1_0010));
+}
+
+#[test]
+fn test_reconstruct_fcnt_over_gap() {
+    assert_eq!(
+        reconstruct_fcnt(5, 5 + MAX_FCNT_GAP as u16 + 1),
+        Err(lorawan::parser::Error::FCntGapTooLarge)
+    );
+    assert_eq!(reconstruct_fcnt(0xfff0, 0x4000), Err(lorawan::parser::Error::FCntGapTooLarge));
+}
+
 #[test]
 fn test_validate_data_mic_when_ok() {
     let phy = EncryptedDataPayload::new(phy_dataup_payload()).unwrap();
@@ -293,6 +584,21 @@ fn test_validate_data_mic_when_not_ok() {
     assert!(!phy.validate_mic(&key, 1));
 }
 
+#[test]
+fn test_validate_data_mic_verbose() {
+    let phy = EncryptedDataPayload::new(phy_dataup_payload()).unwrap();
+    assert_eq!(phy.validate_mic_verbose(&AES128([2; 16]), 1), Ok(()));
+
+    let wrong_key = AES128([3; 16]);
+    let mismatch = phy.validate_mic_verbose(&wrong_key, 1).unwrap_err();
+    assert_eq!(mismatch.expected, phy.mic());
+    assert_ne!(mismatch.computed, mismatch.expected);
+    let mut bytes = phy_dataup_payload();
+    let len = bytes.len();
+    bytes[len - 4..].copy_from_slice(&mismatch.computed.0);
+    assert!(EncryptedDataPayload::new(bytes).unwrap().validate_mic(&wrong_key, 1));
+}
+
 #[test]
 fn test_new_data_payload_is_none_if_bytes_too_short() {
     let bytes = &[0x80, 0x04, 0x03, 0x02, 0x01, 0x00, 0xff, 0x01, 0x02, 0x03, 0x04];
@@ -310,6 +616,16 @@ fn test_f_port_could_be_absent_in_data_payload() {
     assert!(data_payload.f_port().is_none());
 }
 
+#[test]
+fn test_data_payload_fhdr_bytes() {
+    let data = data_payload_with_f_opts();
+    let phy = EncryptedDataPayload::new(&data[..]).unwrap();
+    let f_opts_len = usize::from(phy.fhdr().fctrl().f_opts_len());
+    assert_eq!(f_opts_len, 3);
+    assert_eq!(phy.fhdr_bytes().len(), 7 + f_opts_len);
+    assert_eq!(phy.fhdr_bytes(), &data[1..11]);
+}
+
 #[test]
 fn test_complete_data_payload_fhdr() {
     let app_skey = AES128([1; 16]);
@@ -552,6 +868,41 @@ fn test_data_payload_creator_when_mac_commands_in_payload() {
     );
 }
 
+#[test]
+fn test_data_payload_creator_downlink_mac_commands_in_frm_payload_round_trip() {
+    let mut buf = [0u8; 256];
+    let mut phy = DataPayloadCreator::new(&mut buf).unwrap();
+    let nwk_skey = [2; 16].into();
+    let app_skey = [1; 16].into();
+    let link_adr_req =
+        DownlinkMacCommand::LinkADRReq(LinkADRReqPayload::new(&[0x52, 0xff, 0x00, 0x01]).unwrap());
+    let link_check_ans = DownlinkMacCommand::LinkCheckAns(LinkCheckAnsPayload::from(&[20, 3]));
+    let dev_status_req = DownlinkMacCommand::DevStatusReq(DevStatusReqPayload());
+    // too long for FOpts, so they can only be sent in a FPort 0 FRMPayload
+    let cmds: Vec<&dyn SerializableMacCommand> =
+        vec![&link_adr_req, &link_check_ans, &dev_status_req, &link_adr_req, &link_adr_req];
+    phy.set_uplink(false).set_f_port(0).set_dev_addr(&[4, 3, 2, 1]).set_fcnt(76543);
+    let mut frame = phy.build(b"", &cmds, &nwk_skey, &app_skey, &DefaultFactory).unwrap().to_vec();
+
+    let PhyPayload::Data(DataPayload::Encrypted(encrypted)) = parse(&mut frame[..]).unwrap() else {
+        panic!("Did not parse a data payload");
+    };
+    assert!(encrypted.validate_mic(&nwk_skey, 76543));
+    assert_eq!(encrypted.fhdr().fopts_len(), 0);
+    // the MAC commands are encrypted with the NwkSKey, whatever the AppSKey
+    let decrypted = encrypted.decrypt(Some(&nwk_skey), None, 76543).unwrap();
+    let FRMPayload::MACCommands(mac_cmds) = decrypted.frm_payload() else {
+        panic!("Did not parse MAC commands in the FRMPayload");
+    };
+    let parsed: Vec<DownlinkMacCommand<'_>> =
+        parse_downlink_mac_commands(mac_cmds.data()).collect();
+    assert_eq!(parsed.len(), cmds.len());
+    let expected = [&link_adr_req, &link_check_ans, &dev_status_req, &link_adr_req, &link_adr_req];
+    for (parsed, expected) in parsed.iter().zip(expected) {
+        assert_eq!(parsed, expected);
+    }
+}
+
 #[test]
 fn test_data_payload_creator_when_mac_commands_in_f_opts() {
     let mut buf = [0u8; 256];
@@ -570,6 +921,32 @@ fn test_data_payload_creator_when_mac_commands_in_f_opts() {
         &data_payload_with_f_opts()[..]
     );
 }
+
+#[test]
+fn test_data_payload_creator_mic_covers_f_opts() {
+    let nwk_skey = NwkSKey::from([1; 16]);
+    let app_skey = AppSKey::from([0; 16]);
+    let build_with_ack = |ack: bool| {
+        let mut buf = [0u8; 256];
+        let mut phy = DataPayloadCreator::new(&mut buf).unwrap();
+        let mut mac_cmd = LinkADRAnsCreator::new();
+        mac_cmd.set_channel_mask_ack(ack).set_data_rate_ack(true).set_tx_power_ack(true);
+        let cmds: Vec<&dyn SerializableMacCommand> = vec![&mac_cmd];
+        phy.set_confirmed(false).set_uplink(true).set_dev_addr(&[4, 3, 2, 1]).set_fcnt(0);
+        phy.set_f_port(1);
+        phy.build(b"hello", &cmds[..], &nwk_skey, &app_skey, &DefaultFactory).unwrap().to_vec()
+    };
+    let with_ack = build_with_ack(true);
+    let without_ack = build_with_ack(false);
+    // Only the FOpts byte differs, yet the MIC has to change as well
+    assert_ne!(with_ack[9], without_ack[9]);
+    assert_eq!(with_ack[10..with_ack.len() - 4], without_ack[10..without_ack.len() - 4]);
+    assert_ne!(with_ack[with_ack.len() - 4..], without_ack[without_ack.len() - 4..]);
+    for data in [with_ack, without_ack] {
+        let phy = EncryptedDataPayload::new(data).unwrap();
+        assert!(phy.validate_mic(nwk_skey.inner(), 0));
+    }
+}
 // TODO: test data payload create with piggy_backed mac commands
 
 #[test]
@@ -639,6 +1016,66 @@ fn test_join_accept_creator_long_buffer() {
 
     assert_eq!(phy.build(&key, &DefaultFactory), Ok(&phy_join_accept_payload()[..]));
 }
+#[cfg(feature = "default-crypto")]
+fn build_join_accept(
+    buf: &mut [u8],
+    c_f_list: impl FnOnce(&mut JoinAcceptCreator<&mut [u8]>),
+) -> DecryptedJoinAcceptPayload<Vec<u8>, DefaultFactory> {
+    let key = AppKey::from(app_key());
+    let mut phy = JoinAcceptCreator::new(buf).unwrap();
+    phy.set_app_nonce(&[0xc7, 0x0b, 0x57])
+        .set_net_id(&[0x01, 0x11, 0x22])
+        .set_dev_addr(&[0x80, 0x19, 0x03, 0x02])
+        .set_dl_settings(0)
+        .set_rx_delay(5);
+    c_f_list(&mut phy);
+    let encrypted = phy.build(key.inner(), &DefaultFactory).unwrap().to_vec();
+    let decrypted = DecryptedJoinAcceptPayload::new(encrypted, &key).unwrap();
+    assert_eq!(decrypted.dev_addr(), DevAddr::new(&[0x80, 0x19, 0x03, 0x02][..]).unwrap());
+    assert_eq!(decrypted.rx_delay(), 5);
+    decrypted
+}
+
+#[test]
+#[cfg(feature = "default-crypto")]
+fn test_join_accept_without_c_f_list() {
+    let decrypted = build_join_accept(&mut [0xff; 33], |_| ());
+    assert_eq!(decrypted.c_f_list(), None);
+}
+
+#[test]
+#[cfg(feature = "default-crypto")]
+fn test_join_accept_with_frequency_c_f_list() {
+    let freqs = [
+        Frequency::new_from_raw(&[0x18, 0x4F, 0x84]),
+        Frequency::new_from_raw(&[0xE8, 0x56, 0x84]),
+        Frequency::new_from_raw(&[0xB8, 0x5E, 0x84]),
+        Frequency::new_from_raw(&[0x88, 0x66, 0x84]),
+        Frequency::new_from_raw(&[0x58, 0x6E, 0x84]),
+    ];
+    // a dirty buffer must not leak into the CFListType
+    let decrypted = build_join_accept(&mut [0xff; 33], |phy| {
+        phy.set_c_f_list(freqs).unwrap();
+    });
+    let Some(CfList::DynamicChannel(decoded)) = decrypted.c_f_list() else {
+        panic!("expected a frequency CFList");
+    };
+    let values = decoded.map(|f| f.value());
+    assert_eq!(values, [867_100_000, 867_300_000, 867_500_000, 867_700_000, 867_900_000]);
+}
+
+#[test]
+#[cfg(feature = "default-crypto")]
+fn test_join_accept_with_channel_mask_c_f_list() {
+    let mut channel_mask = ChannelMask::<9>::default();
+    channel_mask.set_channel(8, true);
+    channel_mask.set_channel(65, true);
+    let decrypted = build_join_accept(&mut [0xff; 33], |phy| {
+        phy.set_c_f_list_channel_mask(&channel_mask).unwrap();
+    });
+    assert_eq!(decrypted.c_f_list(), Some(CfList::FixedChannel(channel_mask)));
+}
+
 #[test]
 #[cfg(feature = "default-crypto")]
 fn test_join_accept_creator_short_buffer() {
@@ -734,9 +1171,255 @@ fn test_derive_appskey() {
     assert_eq!(appskey, expect);
 }
 
+#[test]
+fn test_derive_nwk_s_keys_1_1() {
+    let key = NwkKey::from([
+        0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0a, 0x0b, 0x0c, 0x0d, 0x0e,
+        0x0f,
+    ]);
+    let join_request = JoinRequestPayload::new(phy_join_request_payload()).unwrap();
+    let join_accept =
+        DecryptedJoinAcceptPayload::new(phy_join_accept_payload(), &app_key().into()).unwrap();
+    let join_eui = AppEui::try_from(join_request.app_eui().as_ref()).unwrap();
+
+    let (f_nwk_s_int_key, s_nwk_s_int_key, nwk_s_enc_key) = key.derive_nwk_s_keys(
+        &DefaultFactory,
+        &join_accept.app_nonce(),
+        &join_eui,
+        &join_request.dev_nonce(),
+    );
+    //JoinNonce([c7, 0b, 57]), JoinEUI([04, 03, 02, 01, 04, 03, 02, 01]), DevNonce([2d, 10])
+    assert_eq!(
+        f_nwk_s_int_key,
+        FNwkSIntKey::from([
+            0xfe, 0x21, 0x9a, 0x41, 0xf1, 0x31, 0x2f, 0x4a, 0xdb, 0x27, 0xf8, 0xd4, 0xce, 0x0c,
+            0x71, 0x14,
+        ])
+    );
+    assert_eq!(
+        s_nwk_s_int_key,
+        SNwkSIntKey::from([
+            0x3b, 0xca, 0x3b, 0xe3, 0x4c, 0x2f, 0x3a, 0xd0, 0x76, 0x13, 0x8f, 0x43, 0xe8, 0x64,
+            0x16, 0x9f,
+        ])
+    );
+    assert_eq!(
+        nwk_s_enc_key,
+        NwkSEncKey::from([
+            0x41, 0x7b, 0x00, 0x7b, 0x84, 0xe3, 0xfb, 0xf4, 0x54, 0x94, 0xa9, 0x6f, 0x63, 0x27,
+            0x24, 0xf0,
+        ])
+    );
+}
+
+#[test]
+fn test_derive_app_s_key_1_1() {
+    let key = AppKey::from(app_key());
+    let join_request = JoinRequestPayload::new(phy_join_request_payload()).unwrap();
+    let join_accept = DecryptedJoinAcceptPayload::new(phy_join_accept_payload(), &key).unwrap();
+    let join_eui = AppEui::try_from(join_request.app_eui().as_ref()).unwrap();
+
+    let appskey = key.derive_app_s_key(
+        &DefaultFactory,
+        &join_accept.app_nonce(),
+        &join_eui,
+        &join_request.dev_nonce(),
+    );
+    //JoinNonce([c7, 0b, 57]), JoinEUI([04, 03, 02, 01, 04, 03, 02, 01]), DevNonce([2d, 10])
+    let expect = AppSKey::from([
+        0xc8, 0x9a, 0xa2, 0xcb, 0x91, 0x0d, 0xb5, 0xba, 0x3c, 0xbd, 0x4d, 0x2c, 0xae, 0x81, 0xe8,
+        0xc0,
+    ]);
+    // unlike with LoRaWAN 1.0.x, the NetID is not part of the derivation
+    assert_ne!(appskey, join_accept.derive_appskey(&join_request.dev_nonce(), &key));
+    assert_eq!(appskey, expect);
+}
+
+#[test]
+fn test_derive_js_keys() {
+    use core::str::FromStr;
+    let key = NwkKey::from([
+        0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0a, 0x0b, 0x0c, 0x0d, 0x0e,
+        0x0f,
+    ]);
+    // the DevEUI is used in its over the air (LSB) order: [77, 66, 55, 44, 33, 22, 11, 00]
+    let dev_eui = DevEui::from_str("0011223344556677").unwrap();
+
+    let (js_int_key, js_enc_key) = key.derive_js_keys(&DefaultFactory, &dev_eui);
+    assert_eq!(
+        js_int_key,
+        JSIntKey::from([
+            0xc6, 0x50, 0x72, 0x69, 0x2c, 0xcc, 0xe8, 0x40, 0xb5, 0x48, 0xd7, 0x42, 0xff, 0xc4,
+            0x3a, 0x30,
+        ])
+    );
+    assert_eq!(
+        js_enc_key,
+        JSEncKey::from([
+            0x66, 0x7e, 0xd0, 0x18, 0x3a, 0x33, 0x6b, 0xd3, 0x44, 0x08, 0x3d, 0x61, 0x99, 0xc3,
+            0x2a, 0x44,
+        ])
+    );
+}
+
 #[test]
 #[cfg(feature = "with-to-string")]
 fn test_eui64_to_string() {
     let eui = EUI64::new(&[0x12, 0x34, 0x56, 0x78, 0x9a, 0xbc, 0xde, 0xff]).unwrap();
     assert_eq!(eui.to_string(), "123456789abcdeff".to_owned());
 }
+
+#[test]
+fn test_join_accept_mic_v1_0_matches_known_vector() {
+    let decrypted_phy = new_decrypted_join_accept();
+    let data = decrypted_phy.as_bytes();
+    let mic = join_accept_mic(
+        &DefaultFactory,
+        &AES128([1; 16]),
+        MHDR::new(data[0]),
+        &data[1..data.len() - 4],
+        JoinAcceptMicVersion::V1_0,
+    );
+    assert_eq!(mic, decrypted_phy.mic());
+}
+
+#[test]
+fn test_join_accept_mic_v1_1_covers_join_request_fields() {
+    let key = AES128([2; 16]);
+    let payload = [1, 1, 1, 0x13, 0, 0, 4, 3, 2, 1, 0x80, 1];
+    let join_eui = EUI64::from([1, 2, 3, 4, 5, 6, 7, 8]);
+    let dev_nonce = DevNonce::from(0x0102);
+    let v1_1 = |join_req_type| {
+        join_accept_mic(
+            &DefaultFactory,
+            &key,
+            MHDR::new(0x20),
+            &payload,
+            JoinAcceptMicVersion::V1_1 {
+                join_req_type,
+                join_eui: &join_eui,
+                dev_nonce: &dev_nonce,
+            },
+        )
+    };
+
+    let mut block = vec![0xff, 1, 2, 3, 4, 5, 6, 7, 8, 0x01, 0x02, 0x20];
+    block.extend_from_slice(&payload);
+    let mut mac = DefaultFactory.new_mac(&key);
+    mac.input(&block);
+    assert_eq!(v1_1(0xff).0, mac.result()[..4]);
+
+    assert_ne!(v1_1(0xff), v1_1(0x00));
+    assert_ne!(
+        v1_1(0xff),
+        join_accept_mic(
+            &DefaultFactory,
+            &key,
+            MHDR::new(0x20),
+            &payload,
+            JoinAcceptMicVersion::V1_0
+        )
+    );
+}
+
+#[test]
+fn test_join_accept_mic_validation_fails_on_corrupted_byte() {
+    let key = [1; 16].into();
+    let data = phy_join_accept_payload_with_c_f_list();
+    assert!(EncryptedJoinAcceptPayload::new(data.clone())
+        .unwrap()
+        .decrypt(&key)
+        .validate_mic(&key));
+
+    let mut corrupted = data;
+    corrupted[8] ^= 0x01;
+    let decrypted = EncryptedJoinAcceptPayload::new(corrupted.clone()).unwrap().decrypt(&key);
+    assert!(!decrypted.validate_mic(&key));
+    assert_eq!(
+        DecryptedJoinAcceptPayload::new(corrupted, &key),
+        Err(lorawan::parser::Error::InvalidMic)
+    );
+}
+
+#[test]
+fn test_join_accept_validate_mic_v1_1() {
+    let js_int_key = JSIntKey::from([2; 16]);
+    let enc_key = AppKey::from([3; 16]);
+    let join_eui = EUI64::from([1, 2, 3, 4, 5, 6, 7, 8]);
+    let dev_nonce = DevNonce::from(0x0102);
+    let mut data = vec![0x20, 1, 1, 1, 0x13, 0, 0, 4, 3, 2, 1, 0x80, 1, 0, 0, 0, 0];
+    let version = JoinAcceptMicVersion::V1_1 {
+        join_req_type: 0xff,
+        join_eui: &join_eui,
+        dev_nonce: &dev_nonce,
+    };
+    let mic = join_accept_mic(
+        &DefaultFactory,
+        js_int_key.inner(),
+        MHDR::new(0x20),
+        &data[1..13],
+        version,
+    );
+    data[13..].copy_from_slice(&mic.0);
+    // JoinAccepts are encrypted with an AES decrypt operation
+    DefaultFactory.new_dec(enc_key.inner()).decrypt_block(&mut data[1..17]);
+
+    let decrypted = EncryptedJoinAcceptPayload::new(data).unwrap().decrypt(&enc_key);
+    assert!(decrypted.validate_mic_v1_1(&js_int_key, 0xff, &join_eui, &dev_nonce));
+    assert!(!decrypted.validate_mic_v1_1(&js_int_key, 0x00, &join_eui, &dev_nonce));
+    assert!(!decrypted.validate_mic_v1_1(&js_int_key, 0xff, &join_eui, &DevNonce::from(0x0103)));
+    assert!(!decrypted.validate_mic(&AppKey::from([2; 16])));
+}
+
+#[test]
+fn test_runtime_selected_crypto_gives_identical_mics() {
+    use lorawan::default_crypto::Crypto;
+
+    // stands in for a hardware implementation, computing the same primitives in software
+    struct HwFactory;
+    impl CryptoFactory for HwFactory {
+        type E = <DefaultFactory as CryptoFactory>::E;
+        type D = <DefaultFactory as CryptoFactory>::D;
+        type M = <DefaultFactory as CryptoFactory>::M;
+
+        fn new_enc(&self, key: &AES128) -> Self::E {
+            DefaultFactory.new_enc(key)
+        }
+
+        fn new_dec(&self, key: &AES128) -> Self::D {
+            DefaultFactory.new_dec(key)
+        }
+
+        fn new_mac(&self, key: &AES128) -> Self::M {
+            DefaultFactory.new_mac(key)
+        }
+    }
+
+    let nwk_skey = NwkSKey::from([2; 16]);
+    let app_skey = AppSKey::from([1; 16]);
+    let build = |crypto: &Crypto<HwFactory>| {
+        let mut buf = [0u8; 64];
+        let mut phy = DataPayloadCreator::new(&mut buf).unwrap();
+        phy.set_dev_addr(&[4, 3, 2, 1]).set_fcnt(1).set_f_port(1);
+        phy.build(b"hello", &[], &nwk_skey, &app_skey, crypto).unwrap().to_vec()
+    };
+    let software = build(&Crypto::Software(DefaultFactory));
+    let hardware = build(&Crypto::Hardware(HwFactory));
+    assert_eq!(software, hardware);
+    let phy = EncryptedDataPayload::new(hardware).unwrap();
+    assert!(phy.validate_mic(nwk_skey.inner(), 1));
+}
+
+#[test]
+fn test_encrypted_frm_payload() {
+    let data = phy_dataup_payload();
+    let phy = EncryptedDataPayload::new(&data[..]).unwrap();
+    // MHDR (1) + FHDR (7) + FPort (1) precede the FRMPayload, the MIC (4) follows it
+    assert_eq!(phy.encrypted_frm_payload(), Some(&data[9..data.len() - 4]));
+    assert_eq!(phy.encrypted_frm_payload().unwrap().len(), 5);
+
+    // without FPort there is no FRMPayload
+    let data = [0x40, 0x04, 0x03, 0x02, 0x01, 0x00, 0x00, 0x00, 0xd6, 0xc3, 0xb5, 0x82];
+    let phy = EncryptedDataPayload::new(&data[..]).unwrap();
+    assert_eq!(phy.encrypted_frm_payload(), None);
+}
@@ -198,3 +198,62 @@ fn test_build_mac_commands() {
     assert_eq!(build_mac_commands(&cmds[..], &mut res[..]), Ok(expected_len));
     assert_eq!(res, &vec![0x08, 0x02, 0x06, 0xfe, 0x3f][..]);
 }
+
+#[test]
+fn test_build_f_opts_with_answers() {
+    let mut link_adr_ans = LinkADRAnsCreator::new();
+    link_adr_ans.set_channel_mask_ack(true).set_data_rate_ack(false).set_tx_power_ack(true);
+    let mut rx_param_setup_ans = RXParamSetupAnsCreator::new();
+    rx_param_setup_ans.set_channel_ack(true).set_rx2_data_rate_ack(true);
+    let mut dev_status_ans = DevStatusAnsCreator::new();
+    dev_status_ans.set_battery(200).set_margin(-5).unwrap();
+    let cmds: [&dyn SerializableMacCommand; 3] =
+        [&link_adr_ans, &rx_param_setup_ans, &dev_status_ans];
+
+    let mut res = [0; 15];
+    assert_eq!(build_f_opts(&cmds[..], &mut res[..]), Ok(7));
+    assert_eq!(res[..7], [0x03, 0x05, 0x05, 0x03, 0x06, 200, 0x3b]);
+
+    let mut commands = parse_uplink_mac_commands(&res[..7]);
+    match commands.next() {
+        Some(UplinkMacCommand::LinkADRAns(ans)) => {
+            assert!(ans.channel_mask_ack());
+            assert!(!ans.data_rate_ack());
+            assert!(ans.powert_ack());
+            assert!(!ans.ack());
+        }
+        cmd => panic!("expected a LinkADRAns, got {cmd:?}"),
+    }
+    match commands.next() {
+        Some(UplinkMacCommand::RXParamSetupAns(ans)) => {
+            assert!(ans.channel_ack());
+            assert!(ans.rx2_data_rate_ack());
+            assert!(!ans.rx1_dr_offset_ack());
+        }
+        cmd => panic!("expected an RXParamSetupAns, got {cmd:?}"),
+    }
+    match commands.next() {
+        Some(UplinkMacCommand::DevStatusAns(ans)) => {
+            assert_eq!(ans.battery(), 200);
+            assert_eq!(ans.margin(), -5);
+        }
+        cmd => panic!("expected a DevStatusAns, got {cmd:?}"),
+    }
+    assert_eq!(commands.next(), None);
+}
+
+#[test]
+fn test_build_f_opts_too_big() {
+    let dev_status_ans = DevStatusAnsCreator::new();
+    let cmds: [&dyn SerializableMacCommand; 6] = [&dev_status_ans; 6];
+    let mut res = [0; 18];
+    assert_eq!(
+        build_f_opts(&cmds[..], &mut res[..]),
+        Err(lorawan::maccommandcreator::Error::MacCommandsTooBigForFOpts)
+    );
+    assert_eq!(build_f_opts(&cmds[..5], &mut res[..]), Ok(15));
+    assert_eq!(
+        build_f_opts(&cmds[..5], &mut res[..14]),
+        Err(lorawan::maccommandcreator::Error::BufferTooShort)
+    );
+}
@@ -281,6 +281,33 @@ fn test_parse_mac_commands_with_multiple_cmds() {
     assert_eq!(commands.next(), Some(expected));
 }
 
+#[test]
+fn test_parse_uplink_channel_answers() {
+    // NewChannelAns with the frequency accepted but not the data rate range, followed by an
+    // RXParamSetupAns accepting the channel and RX2 data rate but not the RX1 DR offset
+    let data = [0x07, 0x01, 0x05, 0x03];
+    let mut commands = parse_uplink_mac_commands(&data);
+
+    match commands.next() {
+        Some(UplinkMacCommand::NewChannelAns(ans)) => {
+            assert!(ans.channel_freq_ack());
+            assert!(!ans.data_rate_range_ack());
+            assert!(!ans.ack());
+        }
+        cmd => panic!("expected a NewChannelAns, got {cmd:?}"),
+    }
+    match commands.next() {
+        Some(UplinkMacCommand::RXParamSetupAns(ans)) => {
+            assert!(ans.channel_ack());
+            assert!(ans.rx2_data_rate_ack());
+            assert!(!ans.rx1_dr_offset_ack());
+            assert!(!ans.ack());
+        }
+        cmd => panic!("expected an RXParamSetupAns, got {cmd:?}"),
+    }
+    assert_eq!(commands.next(), None);
+}
+
 #[test]
 fn test_parse_mac_commands_with_multiple_cmds_with_payloads() {
     let data = [3, 0, 0, 0, 112, 3, 0, 0, 255, 0];
@@ -297,6 +324,69 @@ fn test_parse_mac_commands_with_multiple_cmds_with_payloads() {
     );
 }
 
+#[test]
+fn test_parse_downlink_mac_commands_fields() {
+    let data = [
+        0x02, 0x0a, 0x03, // LinkCheckAns: margin 10 dB, 3 gateways
+        0x03, 0x52, 0x07, 0x00, 0x11, // LinkADRReq: DR5, TX power 2, channels 0-2, NbTrans 1
+        0x04, 0x02, // DutyCycleReq: 1/4
+        0x06, // DevStatusReq
+        0x08, 0x05, // RXTimingSetupReq: 5 s
+    ];
+    let mut commands = parse_downlink_mac_commands(&data);
+
+    match commands.next() {
+        Some(DownlinkMacCommand::LinkCheckAns(ans)) => {
+            assert_eq!(ans.margin(), 10);
+            assert_eq!(ans.gateway_count(), 3);
+        }
+        cmd => panic!("expected a LinkCheckAns, got {cmd:?}"),
+    }
+    match commands.next() {
+        Some(DownlinkMacCommand::LinkADRReq(req)) => {
+            assert_eq!(req.data_rate(), 5);
+            assert_eq!(req.tx_power(), 2);
+            assert_eq!(req.channel_mask().statuses::<4>(), [true, true, true, false]);
+            assert_eq!(req.redundancy().channel_mask_control(), 1);
+            assert_eq!(req.redundancy().number_of_transmissions(), 1);
+        }
+        cmd => panic!("expected a LinkADRReq, got {cmd:?}"),
+    }
+    match commands.next() {
+        Some(DownlinkMacCommand::DutyCycleReq(req)) => assert_eq!(req.max_duty_cycle_raw(), 2),
+        cmd => panic!("expected a DutyCycleReq, got {cmd:?}"),
+    }
+    assert!(matches!(commands.next(), Some(DownlinkMacCommand::DevStatusReq(_))));
+    match commands.next() {
+        Some(DownlinkMacCommand::RXTimingSetupReq(req)) => assert_eq!(req.delay(), 5),
+        cmd => panic!("expected an RXTimingSetupReq, got {cmd:?}"),
+    }
+    assert_eq!(commands.next(), None);
+}
+
+#[test]
+fn test_parse_mac_commands_depends_on_direction() {
+    // 0x02 is a LinkCheckReq without payload uplink, but a 2 bytes LinkCheckAns downlink
+    let data = [0x02, 0x02, 0x02];
+    let uplink: Vec<UplinkCid> = parse_uplink_mac_commands(&data).map(|c| (&c).into()).collect();
+    assert_eq!(uplink, [UplinkCid::LinkCheckReq; 3]);
+    let downlink: Vec<DownlinkCid> =
+        parse_downlink_mac_commands(&data).map(|c| (&c).into()).collect();
+    assert_eq!(downlink, [DownlinkCid::LinkCheckAns]);
+}
+
+#[test]
+fn test_parse_mac_commands_stops_on_truncated_command() {
+    // DevStatusReq followed by a LinkADRReq missing its last byte
+    let data = [0x06, 0x03, 0x52, 0x07, 0x00];
+    let mut commands = parse_downlink_mac_commands(&data);
+    assert!(matches!(commands.next(), Some(DownlinkMacCommand::DevStatusReq(_))));
+    assert_eq!(commands.next(), None);
+
+    // a LinkADRAns missing its status
+    assert_eq!(parse_uplink_mac_commands(&[0x03]).count(), 0);
+}
+
 fn mac_cmds_payload() -> Vec<u8> {
     vec![LinkCheckReqPayload::cid(), LinkADRAnsPayload::cid(), 0x00]
 }
@@ -371,6 +461,23 @@ fn test_data_rate_range() {
     assert_eq!(drr.min_data_rate(), 0x05);
 }
 
+#[test]
+fn test_data_rate_range_min_0_max_5() {
+    let drr = DataRateRange::new(0x50).unwrap();
+    assert_eq!(drr.min_data_rate(), 0);
+    assert_eq!(drr.max_data_rate(), 5);
+    assert!(drr.contains(0));
+    assert!(drr.contains(5));
+    assert!(!drr.contains(6));
+    assert_eq!(DataRateRange::from_min_max(0, 5), Ok(drr));
+}
+
+#[test]
+fn test_data_rate_range_from_min_max_invalid() {
+    assert!(DataRateRange::from_min_max(5, 0).is_err());
+    assert!(DataRateRange::from_min_max(0, 16).is_err());
+}
+
 #[test]
 fn test_data_rate_range_inversed_min_and_max() {
     let drr = DataRateRange::new(0x5a);
@@ -402,3 +509,56 @@ fn test_mac_commands_len_with_mac_cmds() {
 
     assert_eq!(mac_commands_len(&cmds[..]), 5);
 }
+
+#[test]
+fn test_cid_from_direction_and_byte() {
+    assert_eq!(Cid::try_from((Direction::Uplink, 0x03)), Ok(Cid::Uplink(UplinkCid::LinkADRAns)));
+    assert_eq!(
+        Cid::try_from((Direction::Downlink, 0x03)),
+        Ok(Cid::Downlink(DownlinkCid::LinkADRReq))
+    );
+    assert_eq!(
+        Cid::try_from((Direction::Downlink, 0x0D)),
+        Ok(Cid::Downlink(DownlinkCid::DeviceTimeAns))
+    );
+    assert_eq!(
+        Cid::try_from((Direction::Uplink, 0x0B)),
+        Err(lorawan::maccommands::Error::UnknownMacCommand)
+    );
+    assert_eq!(
+        Cid::try_from((Direction::Downlink, 0x01)),
+        Err(lorawan::maccommands::Error::UnknownMacCommand)
+    );
+}
+
+#[test]
+fn test_cid_round_trip() {
+    for direction in [Direction::Uplink, Direction::Downlink] {
+        for byte in 0..=u8::MAX {
+            if let Ok(cid) = Cid::try_from((direction, byte)) {
+                assert_eq!(cid.direction(), direction);
+                assert_eq!(u8::from(cid), byte);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_cid_from_parsed_command() {
+    let data = [0x02, 0x03, 0x00, 0x06];
+    let cids: Vec<DownlinkCid> = parse_downlink_mac_commands(&data).map(|c| (&c).into()).collect();
+    assert_eq!(cids, [DownlinkCid::LinkCheckAns, DownlinkCid::DevStatusReq]);
+}
+
+#[test]
+fn test_cid_of_creators_and_unknown_cid() {
+    let creator = UplinkMacCommandCreator::DevStatusAns(DevStatusAnsCreator::new());
+    assert_eq!(creator.cid(), UplinkCid::DevStatusAns as u8);
+    let creator = DownlinkMacCommandCreator::DevStatusReq(DevStatusReqCreator::new());
+    assert_eq!(creator.cid(), DownlinkCid::DevStatusReq as u8);
+
+    // parsing stops at 0x0B, which is not a downlink CID
+    let data = [0x06, 0x0B, 0x06];
+    let cids: Vec<DownlinkCid> = parse_downlink_mac_commands(&data).map(|c| (&c).into()).collect();
+    assert_eq!(cids, [DownlinkCid::DevStatusReq]);
+}
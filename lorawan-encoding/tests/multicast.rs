@@ -0,0 +1,189 @@
+use lorawan::maccommands::MacCommandIterator;
+use lorawan::multicast::*;
+use lorawan::parser::DevAddr;
+
+#[test]
+fn test_class_c_session_req() {
+    // Group 1, SessionTime 0x5000_0000, SessionTimeOut 2^6 s, 869.525 MHz, DR0
+    let bytes = [0x01, 0x00, 0x00, 0x00, 0x50, 0x06, 0xd2, 0xad, 0x84, 0x00];
+    let req = McClassCSessionReqPayload::new(&bytes).unwrap();
+    assert_eq!(req.mc_group_id(), 1);
+    assert_eq!(req.session_time(), 0x5000_0000);
+    assert_eq!(req.session_time_out(), 6);
+    assert_eq!(req.dl_frequency().value(), 869_525_000);
+    assert_eq!(req.data_rate(), 0);
+
+    let window = req.session_window();
+    assert_eq!(window, SessionWindow { open: 0x5000_0000, close: 0x5000_0040 });
+    assert_eq!(window.time_to_start(0x5000_0000 - 300), Some(300));
+    assert_eq!(window.time_to_start(0x5000_0000), Some(0));
+    assert_eq!(window.time_to_start(0x5000_0001), None);
+}
+
+#[test]
+fn test_class_b_session_req() {
+    // Group 2, SessionTime 1280 s, SessionTimeOut 2^3 beacon periods, Periodicity 4, 869.525 MHz, DR3
+    let bytes = [0x02, 0x00, 0x05, 0x00, 0x00, 0x43, 0xd2, 0xad, 0x84, 0x03];
+    let req = McClassBSessionReqPayload::new(&bytes).unwrap();
+    assert_eq!(req.mc_group_id(), 2);
+    assert_eq!(req.session_time(), 1280);
+    assert_eq!(req.session_time_out(), 3);
+    assert_eq!(req.periodicity(), 4);
+    assert_eq!(req.ping_period(), 512);
+    assert_eq!(req.dl_frequency().value(), 869_525_000);
+    assert_eq!(req.data_rate(), 3);
+    assert_eq!(req.session_window(), SessionWindow { open: 1280, close: 1280 + 8 * 128 });
+}
+
+#[test]
+fn test_class_b_session_open_time_from_session_time_and_now() {
+    let bytes = [0x02, 0x00, 0x05, 0x00, 0x00, 0x43, 0xd2, 0xad, 0x84, 0x03];
+    let window = McClassBSessionReqPayload::new(&bytes).unwrap().session_window();
+    assert_eq!(window.open, 1280);
+    assert_eq!(window.time_to_start(1000), Some(280));
+    assert_eq!(window.time_to_start(1280), Some(0));
+    assert_eq!(window.time_to_start(1300), None);
+}
+
+#[test]
+fn test_session_window_time_to_start_wraps_around() {
+    let window = SessionWindow { open: 10, close: 74 };
+    assert_eq!(window.time_to_start(u32::MAX - 9), Some(20));
+}
+
+#[test]
+fn test_session_ans_time_to_start() {
+    let ans = McClassCSessionAnsPayload::new(&[0x01, 0x2c, 0x01, 0x00]).unwrap();
+    assert_eq!(ans.mc_group_id(), 1);
+    assert_eq!(ans.time_to_start(), Some(300));
+
+    // FreqError is set
+    let ans = McClassBSessionAnsPayload::new(&[0x0a, 0x00, 0x00, 0x00]).unwrap();
+    assert_eq!(ans.mc_group_id(), 2);
+    assert_eq!(ans.time_to_start(), None);
+}
+
+#[test]
+fn test_group_setup_ans_creator_round_trip() {
+    let mut creator = McGroupSetupAnsCreator::new();
+    creator.set_mc_group_id(3);
+    assert_eq!(creator.build(), &[0x02, 0x03]);
+    let ans = McGroupSetupAnsPayload::new(&creator.build()[1..]).unwrap();
+    assert_eq!(ans.mc_group_id(), 3);
+    assert!(!ans.id_error());
+
+    creator.set_mc_group_id(1).set_id_error(true);
+    assert_eq!(creator.build(), &[0x02, 0x05]);
+    let ans = McGroupSetupAnsPayload::new(&creator.build()[1..]).unwrap();
+    assert_eq!(ans.mc_group_id(), 1);
+    assert!(ans.id_error());
+}
+
+#[test]
+fn test_class_c_session_ans_creator_round_trip() {
+    // Group 0, SessionTime 1000 s, SessionTimeOut 2^10 s, 869.525 MHz, DR0
+    let bytes = [0x00, 0xe8, 0x03, 0x00, 0x00, 0x0a, 0xd2, 0xad, 0x84, 0x00];
+    let req = McClassCSessionReqPayload::new(&bytes).unwrap();
+    assert_eq!(req.dl_frequency().value(), 0x84add2 * 100);
+    let time_to_start = req.session_window().time_to_start(100).unwrap();
+
+    let mut creator = McClassCSessionAnsCreator::new();
+    creator.set_mc_group_id(req.mc_group_id()).set_time_to_start(time_to_start).unwrap();
+    assert_eq!(creator.build(), &[0x04, 0x00, 0x84, 0x03, 0x00]);
+    let ans = McClassCSessionAnsPayload::new(&creator.build()[1..]).unwrap();
+    assert_eq!(ans.mc_group_id(), 0);
+    assert_eq!(ans.time_to_start(), Some(900));
+
+    creator.set_freq_error(true);
+    let ans = McClassCSessionAnsPayload::new(&creator.build()[1..]).unwrap();
+    assert_eq!(ans.time_to_start(), None);
+
+    assert!(creator.set_time_to_start(0x100_0000).is_err());
+}
+
+#[test]
+fn test_class_b_session_ans_creator_round_trip() {
+    let mut creator = McClassBSessionAnsCreator::new();
+    creator.set_mc_group_id(2).set_time_to_start(0x01_0203).unwrap();
+    assert_eq!(creator.build(), &[0x05, 0x02, 0x03, 0x02, 0x01]);
+    let ans = McClassBSessionAnsPayload::new(&creator.build()[1..]).unwrap();
+    assert_eq!(ans.mc_group_id(), 2);
+    assert_eq!(ans.time_to_start(), Some(0x01_0203));
+
+    creator.set_mc_group_undefined(true);
+    let ans = McClassBSessionAnsPayload::new(&creator.build()[1..]).unwrap();
+    assert_eq!(ans.time_to_start(), None);
+}
+
+#[test]
+fn test_group_delete() {
+    let req = McGroupDeleteReqPayload::new(&[0x02]).unwrap();
+    assert_eq!(req.mc_group_id(), 2);
+
+    let mut creator = McGroupDeleteAnsCreator::new();
+    creator.set_mc_group_id(req.mc_group_id());
+    assert_eq!(creator.build(), &[0x03, 0x02]);
+    let ans = McGroupDeleteAnsPayload::new(&creator.build()[1..]).unwrap();
+    assert_eq!(ans.mc_group_id(), 2);
+    assert!(!ans.mc_group_undefined());
+
+    creator.set_mc_group_undefined(true);
+    assert_eq!(creator.build(), &[0x03, 0x06]);
+    let ans = McGroupDeleteAnsPayload::new(&creator.build()[1..]).unwrap();
+    assert!(ans.mc_group_undefined());
+}
+
+#[test]
+fn test_group_status() {
+    let req = McGroupStatusReqPayload::new(&[0x0b]).unwrap();
+    assert_eq!(req.req_group_mask(), 0b1011);
+
+    // groups 0, 1 and 3 are requested, but only groups 0 and 3 are set up
+    let mut creator = McGroupStatusAnsCreator::new();
+    creator.set_nb_total_groups(2);
+    creator.add_group(3, DevAddr::from([0x31, 0x32, 0x33, 0x34]));
+    creator.add_group(0, DevAddr::from([0x01, 0x02, 0x03, 0x04]));
+    assert_eq!(
+        creator.build(),
+        &[0x01, 0x29, 0x00, 0x01, 0x02, 0x03, 0x04, 0x03, 0x31, 0x32, 0x33, 0x34]
+    );
+
+    let ans = McGroupStatusAnsPayload::new(&creator.build()[1..]).unwrap();
+    assert_eq!(ans.len(), 11);
+    assert_eq!(ans.nb_total_groups(), 2);
+    assert_eq!(ans.ans_group_mask(), 0b1001);
+    let mut groups = ans.groups();
+    assert_eq!(groups.next(), Some((0, DevAddr::from([0x01, 0x02, 0x03, 0x04]))));
+    assert_eq!(groups.next(), Some((3, DevAddr::from([0x31, 0x32, 0x33, 0x34]))));
+    assert_eq!(groups.next(), None);
+}
+
+#[test]
+fn test_group_status_ans_without_groups() {
+    let mut creator = McGroupStatusAnsCreator::new();
+    assert_eq!(creator.build(), &[0x01, 0x00]);
+    creator.set_nb_total_groups(1);
+    let ans = McGroupStatusAnsPayload::new(&creator.build()[1..]).unwrap();
+    assert_eq!(ans.len(), 1);
+    assert_eq!(ans.groups().count(), 0);
+    assert!(McGroupStatusAnsPayload::new(&[0x11, 0x00]).is_err());
+}
+
+#[test]
+fn test_parse_group_status_ans() {
+    let data = [0x01, 0x11, 0x00, 0x01, 0x02, 0x03, 0x04, 0x03, 0x00];
+    let mut cmds = MacCommandIterator::<UplinkMulticastMsg<'_>>::new(&data);
+    match cmds.next() {
+        Some(UplinkMulticastMsg::McGroupStatusAns(ans)) => {
+            assert_eq!(ans.nb_total_groups(), 1);
+            assert_eq!(ans.groups().count(), 1);
+        }
+        cmd => panic!("expected a McGroupStatusAns, got {cmd:?}"),
+    }
+    assert!(matches!(cmds.next(), Some(UplinkMulticastMsg::McGroupDeleteAns(_))));
+    assert!(cmds.next().is_none());
+
+    // the last group is truncated
+    let mut cmds = MacCommandIterator::<UplinkMulticastMsg<'_>>::new(&data[..6]);
+    assert!(cmds.next().is_none());
+}
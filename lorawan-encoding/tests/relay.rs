@@ -0,0 +1,56 @@
+use lorawan::creator::DataPayloadCreator;
+use lorawan::default_crypto::DefaultFactory;
+use lorawan::keys::{AppSKey, NwkSKey};
+use lorawan::parser::{DataHeader, EncryptedDataPayload, FRMPayload};
+use lorawan::relay::*;
+
+fn end_device_uplink() -> Vec<u8> {
+    vec![
+        0x40, 0x04, 0x03, 0x02, 0x01, 0x80, 0x01, 0x00, 0x01, 0xa6, 0x94, 0x64, 0x26, 0x15, 0xd6,
+        0xc3, 0xb5, 0x82,
+    ]
+}
+
+#[test]
+fn test_forward_uplink_req_round_trip() {
+    let relay_nwk_skey = NwkSKey::from([1; 16]);
+    let relay_app_skey = AppSKey::from([2; 16]);
+    let metadata = UplinkMetadata { data_rate: 5, snr: -3, rssi: -100, wor_channel: 1 };
+
+    let mut forward_buf = [0u8; 64];
+    let mut creator = ForwardUplinkReqCreator::new(&mut forward_buf[..]);
+    let forward = creator.build(&metadata, 868_100_000, &end_device_uplink()).unwrap();
+    let mut buf = [0u8; 128];
+    let mut phy = DataPayloadCreator::new(&mut buf[..]).unwrap();
+    phy.set_uplink(true).set_dev_addr(&[1, 2, 3, 4]).set_fcnt(7).set_f_port(RELAY_FPORT);
+    let relay_uplink = phy
+        .build(forward, &[], &relay_nwk_skey, &relay_app_skey, &DefaultFactory)
+        .unwrap()
+        .to_vec();
+
+    let phy = EncryptedDataPayload::new(relay_uplink).unwrap();
+    assert!(phy.validate_mic(relay_nwk_skey.inner(), 7));
+    assert_eq!(phy.f_port(), Some(RELAY_FPORT));
+    let phy = phy.decrypt(None, Some(relay_app_skey.inner()), 7).unwrap();
+    let FRMPayload::Data(data) = phy.frm_payload() else {
+        panic!("expected a data FRMPayload");
+    };
+    let forwarded = ForwardUplinkReq::new(data).unwrap();
+    assert_eq!(forwarded.metadata(), metadata);
+    assert_eq!(forwarded.frequency().value(), 868_100_000);
+    assert_eq!(forwarded.phy_payload(), &end_device_uplink()[..]);
+}
+
+#[test]
+fn test_forward_uplink_req_too_short() {
+    assert!(ForwardUplinkReq::new(&[0u8; 6][..]).is_err());
+}
+
+#[test]
+fn test_forward_uplink_req_creator_invalid_metadata() {
+    let mut buf = [0u8; 64];
+    let metadata = UplinkMetadata { data_rate: 5, snr: 12, rssi: -100, wor_channel: 0 };
+    assert!(ForwardUplinkReqCreator::new(&mut buf[..])
+        .build(&metadata, 868_100_000, &end_device_uplink())
+        .is_err());
+}
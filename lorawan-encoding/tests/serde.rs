@@ -0,0 +1,52 @@
+#![cfg(feature = "serde")]
+use lorawan::keys::*;
+
+fn app_key() -> AppKey {
+    AppKey::from([
+        0x00, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88, 0x99, 0xaa, 0xbb, 0xcc, 0xdd, 0xee,
+        0xff,
+    ])
+}
+
+fn dev_eui() -> DevEui {
+    DevEui::from([0xf0, 0xde, 0xbc, 0x9a, 0x78, 0x56, 0x34, 0x12])
+}
+
+fn to_cbor<T: serde::Serialize>(value: &T) -> Vec<u8> {
+    let mut res = Vec::new();
+    ciborium::into_writer(value, &mut res).unwrap();
+    res
+}
+
+#[test]
+fn test_key_json_hex_msb() {
+    let json = serde_json::to_string(&app_key()).unwrap();
+    assert_eq!(json, "\"00112233445566778899aabbccddeeff\"");
+    assert_eq!(serde_json::from_str::<AppKey>(&json).unwrap(), app_key());
+
+    let aes128 = *app_key().inner();
+    let json = serde_json::to_string(&aes128).unwrap();
+    assert_eq!(json, "\"00112233445566778899aabbccddeeff\"");
+    assert_eq!(serde_json::from_str::<AES128>(&json).unwrap(), aes128);
+}
+
+#[test]
+fn test_eui_json_hex_lsb() {
+    let json = serde_json::to_string(&dev_eui()).unwrap();
+    assert_eq!(json, "\"123456789abcdef0\"");
+    assert_eq!(serde_json::from_str::<DevEui>(&json).unwrap(), dev_eui());
+    assert!(serde_json::from_str::<DevEui>("\"123456789abcdef\"").is_err());
+    assert!(serde_json::from_str::<DevEui>("[240,222,188,154,120,86,52,18]").is_err());
+}
+
+#[test]
+fn test_binary_keeps_byte_arrays() {
+    // CBOR is not human-readable, so the bytes are serialized as with a derived implementation
+    let cbor = to_cbor(&dev_eui());
+    assert_eq!(cbor, to_cbor(&[0xf0u8, 0xde, 0xbc, 0x9a, 0x78, 0x56, 0x34, 0x12]));
+    assert_eq!(ciborium::from_reader::<DevEui, _>(&cbor[..]).unwrap(), dev_eui());
+
+    let cbor = to_cbor(&app_key());
+    assert_eq!(cbor, to_cbor(app_key().as_bytes()));
+    assert_eq!(ciborium::from_reader::<AppKey, _>(&cbor[..]).unwrap(), app_key());
+}
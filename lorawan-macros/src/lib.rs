@@ -35,6 +35,8 @@ pub fn derive_command_handler(input: proc_macro::TokenStream) -> proc_macro::Tok
 
     // Parse enum members into list of (Command, Payload, Attributes) tuples
     let members = parse_enum_members(&input);
+    // Optional enum of the CIDs, with a variant named after each command
+    let cid_type = parse_cid_type(&input.attrs);
     let mut impl_len = Vec::new();
     let mut impl_bytes = Vec::new();
     let mut impl_cid = Vec::new();
@@ -47,6 +49,7 @@ pub fn derive_command_handler(input: proc_macro::TokenStream) -> proc_macro::Tok
     let mut creator_enum_len = Vec::new();
     let mut creator_enum_build = Vec::new();
     let mut creator_enum_impl_cid = Vec::new();
+    let mut cid_type_from = Vec::new();
 
     for (n, payload, attributes) in members {
         let n = n.clone();
@@ -66,37 +69,45 @@ pub fn derive_command_handler(input: proc_macro::TokenStream) -> proc_macro::Tok
         });
 
         // SerializableMacCommand::cid()
+        let cid_value = match cid_type {
+            Some(ref ct) => quote! { #ct::#n as u8 },
+            None => quote! { #t::cid() },
+        };
         impl_cid.push(quote! {
-            Self::#n(_) => #t::cid()
+            Self::#n(_) => #cid_value
+        });
+        cid_type_from.push(quote! {
+            #handler::#n(_) => Self::#n
         });
 
         // SerializableMacCommand::next()
         // Different iterator implementation for fixed and variable length
         if let Some((_, ref len_opt)) = attributes.attrs {
-            match len_opt {
-                Some(_) => {
-                    impl_iter_next.push(quote! {
-                        if data[0] == #t::cid() && data.len() >= #t::max_len() {
-                            self.index = self.index + #t::max_len() + 1;
-                            Some(#handler::#n(#t::new_from_raw(&data[1..1 + #t::max_len()])))
-                        } else
-                    });
-                }
-                None => {
-                    impl_iter_next.push(quote! {
-                        if data[0] == #t::cid() {
-                            let payload = #t::new_from_raw(&data[1..]);
-                            let len = payload.len();
-                            if data.len() >= len {
-                                self.index = self.index + len + 1;
-                                Some(#handler::#n(payload))
-                            } else {
-                                None
-                            }
-                        } else
-                    });
-                }
-            }
+            let parse = match len_opt {
+                Some(_) => quote! {
+                    if data.len() > #t::max_len() {
+                        self.index = self.index + #t::max_len() + 1;
+                        Some(#handler::#n(#t::new_from_raw(&data[1..1 + #t::max_len()])))
+                    } else {
+                        None
+                    }
+                },
+                None => quote! {
+                    let payload = #t::new_from_raw(&data[1..]);
+                    let len = payload.len();
+                    // the data includes the CID
+                    if data.len() > len {
+                        self.index = self.index + len + 1;
+                        Some(#handler::#n(payload))
+                    } else {
+                        None
+                    }
+                },
+            };
+            impl_iter_next.push(match cid_type {
+                Some(ref ct) => quote! { Ok(#ct::#n) => { #parse } },
+                None => quote! { if data[0] == #t::cid() { #parse } else },
+            });
         }
 
         // CommandCreator enum
@@ -110,8 +121,9 @@ pub fn derive_command_handler(input: proc_macro::TokenStream) -> proc_macro::Tok
         creator_enum_build.push(quote! {
             Self::#n(ref c) => c.build()
         });
-        creator_enum_impl_cid.push(quote! {
-            Self::#n(c) => c.cid()
+        creator_enum_impl_cid.push(match cid_type {
+            Some(ref ct) => quote! { Self::#n(_) => #ct::#n as u8 },
+            None => quote! { Self::#n(c) => c.cid() },
         });
 
         // Generate definition and common implementation for payloads
@@ -236,6 +248,12 @@ pub fn derive_command_handler(input: proc_macro::TokenStream) -> proc_macro::Tok
                 }
             }
 
+            // Variable-length payloads give their length from the data written so far
+            let creator_len = match (len_opt, lt) {
+                (None, Some(_)) => quote! { #t::new_from_raw(&self.data[1..]).len() + 1 },
+                _ => quote! { #t::max_len() + 1 },
+            };
+
             let payload_creator = Ident::new(&format!("{}Creator", n), Span::call_site());
             payload_struct_creator_impls.push(quote! {
                 #[derive(Debug)]
@@ -263,13 +281,40 @@ pub fn derive_command_handler(input: proc_macro::TokenStream) -> proc_macro::Tok
                     /// Get the length including CID.
                     #[allow(clippy::len_without_is_empty)]
                     pub fn len(&self) -> usize {
-                        #t::max_len() + 1
+                        #creator_len
                     }
                 }
             });
         }
     }
 
+    let iter_next = match cid_type {
+        Some(ref ct) => quote! {
+            match #ct::try_from(data[0]) {
+                #( #impl_iter_next )*
+                Err(_) => None,
+            }
+        },
+        None => quote! {
+            #( #impl_iter_next )*
+            {
+                None
+            }
+        },
+    };
+
+    let cid_type_impls = cid_type.map(|ct| {
+        quote! {
+            impl #handler_lt From<&#handler #handler_lt> for #ct {
+                fn from(cmd: &#handler #handler_lt) -> Self {
+                    match cmd {
+                        #( #cid_type_from ),*
+                    }
+                }
+            }
+        }
+    });
+
     // Generate the final implementations
     quote! {
         impl #handler_lt #handler #handler_lt {
@@ -308,16 +353,15 @@ pub fn derive_command_handler(input: proc_macro::TokenStream) -> proc_macro::Tok
             fn next(&mut self) -> Option<Self::Item> {
                 if self.index < self.data.len() {
                     let data = &self.data[self.index..];
-                    #( #impl_iter_next )*
-                    {
-                        None
-                    }
+                    #iter_next
                 } else {
                     None
                 }
             }
         }
 
+        #cid_type_impls
+
         #( #payload_struct_impls )*
 
         #( #payload_struct_creator_impls )*
@@ -423,7 +467,7 @@ fn attr_handle_cmd(attr: &syn::Attribute) -> Option<(syn::Expr, Option<syn::Expr
                                 len = Some(v.value);
                             }
                             &_ => {
-                                panic!("Invalid argument: {}", &id);
+                                panic!("Invalid argument: {}", id);
                             }
                         }
                     } else {
@@ -437,6 +481,22 @@ fn attr_handle_cmd(attr: &syn::Attribute) -> Option<(syn::Expr, Option<syn::Expr
     cid.map(|cid| (cid, len))
 }
 
+/// Handler for the `#[cmd(cid_type = ...)]` attribute of the enum, naming the enum of its CIDs
+fn parse_cid_type(input: &[syn::Attribute]) -> Option<syn::Expr> {
+    let attr = input.iter().find(|attr| attr.path().is_ident("cmd"))?;
+    let nested = attr
+        .parse_args_with(syn::punctuated::Punctuated::<Meta, syn::Token![,]>::parse_terminated)
+        .ok()?;
+    let mut cid_type = None;
+    for meta in nested {
+        match meta {
+            Meta::NameValue(v) if v.path.is_ident("cid_type") => cid_type = Some(v.value),
+            _ => panic!("Invalid enum argument, only cid_type is supported!"),
+        }
+    }
+    cid_type
+}
+
 /// Collect supported attributes for enum members into [`Attributes`]:
 /// * docstring
 /// * `cmd(..)` - used to specify size and CID for payload